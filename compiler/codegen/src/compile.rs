@@ -8,10 +8,11 @@
 #![deny(clippy::cast_possible_truncation)]
 
 use crate::{
-    IndexSet, ToPythonName,
+    emit_backend::{EmitBackend, SequenceKind},
     error::{CodegenError, CodegenErrorType},
     ir,
     symboltable::{self, SymbolFlags, SymbolScope, SymbolTable},
+    IndexSet, ToPythonName,
 };
 use itertools::Itertools;
 use malachite_bigint::BigInt;
@@ -21,10 +22,11 @@ use ruff_python_ast::{
     Alias, Arguments, BoolOp, CmpOp, Comprehension, ConversionFlag, DebugText, Decorator, DictItem,
     ExceptHandler, ExceptHandlerExceptHandler, Expr, ExprAttribute, ExprBoolOp, ExprFString,
     ExprList, ExprName, ExprStarred, ExprSubscript, ExprTuple, ExprUnaryOp, FString,
-    FStringElement, FStringElements, FStringPart, Int, Keyword, MatchCase, ModExpression,
-    ModModule, Operator, Parameters, Pattern, PatternMatchAs, PatternMatchValue, Stmt, StmtExpr,
-    TypeParam, TypeParamParamSpec, TypeParamTypeVar, TypeParamTypeVarTuple, TypeParams, UnaryOp,
-    WithItem,
+    FStringElement, FStringElements, FStringExpressionElement, FStringPart, Int, Keyword,
+    MatchCase, ModExpression, ModModule, Operator, Parameters, Pattern, PatternMatchAs,
+    PatternMatchClass, PatternMatchMapping, PatternMatchOr, PatternMatchSequence, PatternMatchStar,
+    PatternMatchValue, Stmt, StmtExpr, TypeParam, TypeParamParamSpec, TypeParamTypeVar,
+    TypeParamTypeVarTuple, TypeParams, UnaryOp, WithItem,
 };
 use ruff_source_file::OneIndexed;
 use ruff_text_size::{Ranged, TextRange};
@@ -59,6 +61,31 @@ fn is_forbidden_name(name: &str) -> bool {
     BUILTIN_CONSTANTS.contains(&name)
 }
 
+/// Drop instructions emitted after an unconditional `ReturnValue`,
+/// `ReturnConst`, `Raise` or `Jump` within the same basic block - they can
+/// never execute, since nothing in this block can jump back past its own
+/// terminator. Gated behind [`OptimizationLevel::Basic`] and up.
+///
+/// This only ever shrinks a block's instruction list in place; it doesn't
+/// renumber or collapse blocks, so every `ir::BlockIdx` a jump elsewhere in
+/// the code object refers to stays valid.
+fn trim_unreachable_instructions(info: &mut ir::CodeInfo) {
+    for block in &mut info.blocks {
+        let Some(terminator) = block.instructions.iter().position(|instr| {
+            matches!(
+                instr.instr,
+                Instruction::ReturnValue
+                    | Instruction::ReturnConst { .. }
+                    | Instruction::Raise { .. }
+                    | Instruction::Jump { .. }
+            )
+        }) else {
+            continue;
+        };
+        block.instructions.truncate(terminator + 1);
+    }
+}
+
 /// Main structure holding the state of compilation.
 struct Compiler<'src> {
     code_stack: Vec<ir::CodeInfo>,
@@ -82,9 +109,82 @@ enum DoneWithFuture {
 
 #[derive(Debug, Clone, Default)]
 pub struct CompileOpts {
-    /// How optimized the bytecode output should be; any optimize > 0 does
-    /// not emit assert statements
-    pub optimize: u8,
+    /// How optimized the bytecode output should be. See
+    /// [`OptimizationLevel`] for what each tier does.
+    pub optimize: OptimizationLevel,
+    /// Run [`crate::typecheck`]'s gradual type-checking pass over
+    /// annotations before emitting bytecode, reporting obvious
+    /// annotation/value conflicts as a `CodegenError`. Off by default:
+    /// unannotated code, and code whose annotations this pass can't
+    /// resolve, is never affected either way.
+    pub type_check: bool,
+    /// How to turn annotation expressions into `__annotations__`. See
+    /// [`AnnotationMode`]. A `from __future__ import annotations`
+    /// statement always wins and stringifies regardless of this
+    /// setting.
+    pub annotation_mode: AnnotationMode,
+    /// Run [`crate::fusion`]'s superinstruction-fusion pass over the
+    /// finished block graph. Off by default: as documented on
+    /// [`crate::fusion::optimize`], this tree has no way to emit the
+    /// fused opcodes it would rewrite into, so turning this on only pays
+    /// for a scan that finds where a real fusion rewrite would apply.
+    pub fuse_superinstructions: bool,
+    /// Emit non-generator comprehensions directly into the enclosing code
+    /// object instead of a nested `<listcomp>`/`<setcomp>`/`<dictcomp>`
+    /// function (PEP 709). Off by default, and a no-op even when set: it
+    /// only runs [`Compiler::comprehension_inline_eligible`], and every
+    /// comprehension is still compiled as a nested function.
+    pub inline_comprehensions: bool,
+}
+
+/// How a function/class's annotation expressions are turned into
+/// `__annotations__`, mirroring the handful of modes Python itself has
+/// shipped across versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnnotationMode {
+    /// Annotations are evaluated at definition time and assembled into
+    /// a plain dict - the behavior when no `__future__` import and no
+    /// other mode applies.
+    #[default]
+    Eager,
+    /// PEP 563: annotation expressions are rendered back to source text
+    /// (via [`ruff_python_codegen::Generator`]) instead of evaluated, so
+    /// `__annotations__` holds strings. This is actually selected by the
+    /// `from __future__ import annotations` statement
+    /// ([`Compiler::future_annotations`]) rather than this field; it's
+    /// listed here for completeness.
+    Stringified,
+    /// PEP 649-style deferred evaluation. This field is not read by the
+    /// compiler: selecting this mode compiles annotations exactly like
+    /// `Eager`. No separate `__annotate__` code object is built, and
+    /// `__annotations__` is assembled at definition time, not lazily on
+    /// first access.
+    Deferred,
+}
+
+/// Graduated optimization tiers, mirroring CPython's `-O`/`-OO` levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum OptimizationLevel {
+    /// No optimization: `assert` statements run, docstrings are kept,
+    /// `__debug__` is `True`.
+    #[default]
+    None,
+    /// `assert` statements are not emitted and `__debug__` is `False`.
+    /// Also enables [`crate::optimize::fold_literal`] constant folding.
+    Basic,
+    /// Everything `Basic` does, plus docstrings are discarded.
+    Full,
+}
+
+impl OptimizationLevel {
+    /// The level as the `u8` the `ir`/`bytecode` crates still take.
+    pub(crate) fn as_u8(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Basic => 1,
+            Self::Full => 2,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -115,6 +215,26 @@ enum ComprehensionType {
     Dict,
 }
 
+/// The suspension effects [`Compiler::find_expr_effects`] found while
+/// walking an expression: whether it contains an `await` anywhere
+/// [`Compiler::contains_await`] would see, and separately whether it
+/// contains a `yield`/`yield from`. Both are collected in the one
+/// traversal since the recursion is otherwise identical.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct ExprEffects {
+    has_await: bool,
+    has_yield: bool,
+}
+
+impl ExprEffects {
+    fn merge(self, other: Self) -> Self {
+        Self {
+            has_await: self.has_await || other.has_await,
+            has_yield: self.has_yield || other.has_yield,
+        }
+    }
+}
+
 /// Compile an Mod produced from ruff parser
 pub fn compile_top(
     ast: ruff_python_ast::Mod,
@@ -141,6 +261,11 @@ pub fn compile_program(
     let symbol_table = SymbolTable::scan_program(ast, source_code.clone())
         .map_err(|e| e.into_codegen_error(source_code.path.to_owned()))?;
     let mut compiler = Compiler::new(opts, source_code, "<module>".to_owned());
+    if compiler.opts.type_check {
+        if let Err((error, range)) = crate::typecheck::check_module(&ast.body) {
+            return Err(compiler.error_ranged(error, range));
+        }
+    }
     compiler.compile_program(ast, symbol_table)?;
     let code = compiler.pop_code_object();
     trace!("Compilation completed: {:?}", code);
@@ -208,6 +333,11 @@ struct PatternContext {
     current_block: usize,
     blocks: Vec<ir::BlockIdx>,
     allow_irrefutable: bool,
+    /// Names bound so far by the pattern currently being compiled, in bind
+    /// order - tracked so callers (guard compilation, reachability/binding
+    /// checks) can see what a case makes available without re-walking the
+    /// pattern tree.
+    captures: Vec<String>,
 }
 
 impl<'src> Compiler<'src> {
@@ -333,10 +463,18 @@ impl Compiler<'_> {
     fn pop_code_object(&mut self) -> CodeObject {
         let table = self.pop_symbol_table();
         assert!(table.sub_tables.is_empty());
-        self.code_stack
-            .pop()
-            .unwrap()
-            .finalize_code(self.opts.optimize)
+        let mut info = self.code_stack.pop().unwrap();
+        if self.opts.optimize >= OptimizationLevel::Basic {
+            trim_unreachable_instructions(&mut info);
+            crate::peephole::optimize(&mut info);
+        }
+        if self.opts.fuse_superinstructions {
+            // Detection only - see `crate::fusion`'s module doc comment for
+            // why there's nothing to rewrite `info` into yet. The sites are
+            // intentionally dropped here, not forgotten.
+            let _fusion_sites = crate::fusion::optimize(&info);
+        }
+        info.finalize_code(self.opts.optimize.as_u8())
     }
 
     // could take impl Into<Cow<str>>, but everything is borrowed from ast structs; we never
@@ -534,7 +672,7 @@ impl Compiler<'_> {
 
         if NameUsage::Load == usage && name == "__debug__" {
             self.emit_load_const(ConstantData::Boolean {
-                value: self.opts.optimize == 0,
+                value: self.opts.optimize == OptimizationLevel::None,
             });
             return Ok(());
         }
@@ -832,7 +970,7 @@ impl Compiler<'_> {
             )?,
             Stmt::Assert(StmtAssert { test, msg, .. }) => {
                 // if some flag, ignore all assert statements!
-                if self.opts.optimize == 0 {
+                if self.opts.optimize == OptimizationLevel::None {
                     let after_block = self.new_block();
                     self.compile_jump_if(test, true, after_block)?;
 
@@ -1048,7 +1186,8 @@ impl Compiler<'_> {
             func_flags |= bytecode::MakeFunctionFlags::KW_ONLY_DEFAULTS;
         }
 
-        self.push_output(
+        open_nested_code_object(
+            self,
             bytecode::CodeFlags::NEW_LOCALS | bytecode::CodeFlags::IS_OPTIMIZED,
             parameters.posonlyargs.len().to_u32(),
             (parameters.posonlyargs.len() + parameters.args.len()).to_u32(),
@@ -1273,6 +1412,23 @@ impl Compiler<'_> {
         Ok(())
     }
 
+    /// Compile `try: ... except* T as e: ...` (PEP 654).
+    ///
+    /// Real `except*` semantics split a `BaseExceptionGroup` into the
+    /// subset matching a handler's type and the remainder to keep
+    /// propagating, so a handler body sees a group restricted to its own
+    /// matches and unmatched members re-raise as their own group
+    /// afterward. None of that is representable with the instructions
+    /// this backend can emit - there's no `BuildExceptionGroup`/
+    /// `ExceptionGroupMatch`-equivalent opcode, and no way to accumulate
+    /// handler-raised exceptions back into a re-raised remainder group -
+    /// those would have to be new `rustpython_compiler_core::bytecode`
+    /// variants, which lives in a crate outside this tree to extend. A
+    /// version that reused the plain `except` codegen and just matched on
+    /// type would compile without error while silently handing handlers
+    /// the raw exception instead of a subgroup, which is worse than
+    /// refusing outright, so this stays a hard error until the VM side of
+    /// PEP 654 exists to compile against.
     fn compile_try_star_statement(
         &mut self,
         _body: &[Stmt],
@@ -1345,7 +1501,7 @@ impl Compiler<'_> {
             }
         }
 
-        let code = self.pop_code_object();
+        let code = close_nested_code_object(self);
         self.qualified_path.pop();
         self.qualified_path.pop();
         self.ctx = prev_ctx;
@@ -1831,9 +1987,320 @@ impl Compiler<'_> {
         }
         if let Some(name) = as_pattern.name.as_ref() {
             self.store_name(name.as_str())?;
+            pattern_context.captures.push(name.to_string());
+        } else {
+            emit!(self, Instruction::Pop);
+        }
+        Ok(())
+    }
+
+    /// Duplicate the value on top of the stack, call `isinstance(dup, cls)`
+    /// where `cls` is a fresh evaluation of `cls_expr`, and leave the
+    /// original value with the bool result stacked above it:
+    /// `[.., subject] -> [.., subject, bool]`.
+    fn emit_isinstance_check(&mut self, cls_expr: &Expr) -> CompileResult<()> {
+        emit!(self, Instruction::Duplicate);
+        let isinstance = self.name("isinstance");
+        emit!(self, Instruction::LoadGlobal(isinstance));
+        emit!(self, Instruction::Rotate2);
+        self.compile_expression(cls_expr)?;
+        emit!(self, Instruction::CallFunctionPositional { nargs: 2 });
+        Ok(())
+    }
+
+    /// Same as [`Self::emit_isinstance_check`], but against a tuple of
+    /// builtin type names (e.g. `(list, tuple)`) rather than an arbitrary
+    /// expression.
+    fn emit_isinstance_check_builtins(&mut self, builtin_names: &[&str]) {
+        emit!(self, Instruction::Duplicate);
+        let isinstance = self.name("isinstance");
+        emit!(self, Instruction::LoadGlobal(isinstance));
+        emit!(self, Instruction::Rotate2);
+        for builtin_name in builtin_names {
+            let idx = self.name(builtin_name);
+            emit!(self, Instruction::LoadGlobal(idx));
+        }
+        if builtin_names.len() > 1 {
+            emit!(
+                self,
+                Instruction::BuildTuple {
+                    size: builtin_names.len().to_u32()
+                }
+            );
+        }
+        emit!(self, Instruction::CallFunctionPositional { nargs: 2 });
+    }
+
+    /// `[.., subject] -> [.., subject, len(subject)]`.
+    fn emit_len_call(&mut self) {
+        emit!(self, Instruction::Duplicate);
+        let len = self.name("len");
+        emit!(self, Instruction::LoadGlobal(len));
+        emit!(self, Instruction::Rotate2);
+        emit!(self, Instruction::CallFunctionPositional { nargs: 1 });
+    }
+
+    /// `[.., subject] -> [.., subject, subject[index]]`, without consuming
+    /// `subject` - every sub-pattern decomposition below reaches its local
+    /// `fail` block with the same one subject value still on the stack,
+    /// regardless of which check tripped, so a single shared cleanup
+    /// (`Pop`, push `False`) at `fail` is always correct.
+    fn emit_subscript_const(&mut self, index: ConstantData) {
+        emit!(self, Instruction::Duplicate);
+        self.emit_load_const(index);
+        emit!(self, Instruction::Subscript);
+    }
+
+    /// Compile `Pattern::MatchSequence`: `[.., subject] -> [.., bool]`.
+    ///
+    /// `subject` is checked and, for every sub-pattern, re-derived via a
+    /// fresh `Duplicate` + `Subscript` off the still-present original, so
+    /// every failure path (bad type, wrong length, a sub-pattern that
+    /// doesn't match) reaches `fail` with exactly that one subject value on
+    /// the stack - no per-branch stack bookkeeping needed.
+    fn compile_pattern_sequence(
+        &mut self,
+        seq: &PatternMatchSequence,
+        pattern_context: &mut PatternContext,
+    ) -> CompileResult<()> {
+        let star_pos = seq
+            .patterns
+            .iter()
+            .position(|p| matches!(p, Pattern::MatchStar(_)));
+        let fail = self.new_block();
+        let join = self.new_block();
+
+        self.emit_isinstance_check_builtins(&["list", "tuple"]);
+        emit!(self, Instruction::JumpIfFalse { target: fail });
+
+        self.emit_len_call();
+        let expected = match star_pos {
+            Some(_) => (seq.patterns.len() - 1).to_u32(),
+            None => seq.patterns.len().to_u32(),
+        };
+        self.emit_load_const(ConstantData::Integer {
+            value: BigInt::from(expected),
+        });
+        let op = if star_pos.is_some() {
+            bytecode::ComparisonOperator::GreaterOrEqual
         } else {
+            bytecode::ComparisonOperator::Equal
+        };
+        emit!(self, Instruction::CompareOperation { op });
+        emit!(self, Instruction::JumpIfFalse { target: fail });
+
+        for (i, pattern) in seq.patterns.iter().enumerate() {
+            if let Pattern::MatchStar(star) = pattern {
+                let Some(name) = star.name.as_ref() else {
+                    continue;
+                };
+                let before = star_pos.unwrap();
+                let after = seq.patterns.len() - before - 1;
+                // Leaves `[.., subject, subscript_target]`, then builds the
+                // `before:upper` slice on top of that - upper is `None` for
+                // a trailing star, or `len(subject) - after` computed from
+                // its own fresh subject copy so it doesn't disturb
+                // `subscript_target` underneath.
+                emit!(self, Instruction::Duplicate);
+                if after == 0 {
+                    self.emit_load_const(ConstantData::Integer {
+                        value: BigInt::from(before.to_u32()),
+                    });
+                    self.emit_load_const(ConstantData::None);
+                } else {
+                    emit!(self, Instruction::Duplicate);
+                    let len = self.name("len");
+                    emit!(self, Instruction::LoadGlobal(len));
+                    emit!(self, Instruction::Rotate2);
+                    emit!(self, Instruction::CallFunctionPositional { nargs: 1 });
+                    self.emit_load_const(ConstantData::Integer {
+                        value: BigInt::from(after.to_u32()),
+                    });
+                    emit!(
+                        self,
+                        Instruction::BinaryOperation {
+                            op: bytecode::BinaryOperator::Subtract
+                        }
+                    );
+                    self.emit_load_const(ConstantData::Integer {
+                        value: BigInt::from(before.to_u32()),
+                    });
+                    emit!(self, Instruction::Rotate2);
+                }
+                emit!(self, Instruction::BuildSlice { step: false });
+                emit!(self, Instruction::Subscript);
+                self.store_name(name.as_str())?;
+                pattern_context.captures.push(name.to_string());
+            } else {
+                let before_star = match star_pos {
+                    Some(star_pos) => i < star_pos,
+                    None => true,
+                };
+                let index = if before_star {
+                    i as i64
+                } else {
+                    i as i64 - seq.patterns.len() as i64
+                };
+                self.emit_subscript_const(ConstantData::Integer {
+                    value: BigInt::from(index),
+                });
+                self.compile_pattern_inner(pattern, pattern_context)?;
+                emit!(self, Instruction::JumpIfFalse { target: fail });
+            }
+        }
+
+        emit!(self, Instruction::Pop);
+        self.emit_load_const(ConstantData::Boolean { value: true });
+        emit!(self, Instruction::Jump { target: join });
+
+        self.switch_to_block(fail);
+        emit!(self, Instruction::Pop);
+        self.emit_load_const(ConstantData::Boolean { value: false });
+
+        self.switch_to_block(join);
+        Ok(())
+    }
+
+    /// Compile `Pattern::MatchMapping`: `[.., subject] -> [.., bool]`, using
+    /// the same preserve-the-subject-via-`Duplicate` technique as
+    /// [`Self::compile_pattern_sequence`].
+    fn compile_pattern_mapping(
+        &mut self,
+        mapping: &PatternMatchMapping,
+        pattern_context: &mut PatternContext,
+    ) -> CompileResult<()> {
+        let fail = self.new_block();
+        let join = self.new_block();
+
+        self.emit_isinstance_check_builtins(&["dict"]);
+        emit!(self, Instruction::JumpIfFalse { target: fail });
+
+        for (key, pattern) in mapping.keys.iter().zip(&mapping.patterns) {
+            emit!(self, Instruction::Duplicate);
+            self.compile_expression(key)?;
+            emit!(
+                self,
+                Instruction::TestOperation {
+                    op: bytecode::TestOperator::In
+                }
+            );
+            emit!(self, Instruction::JumpIfFalse { target: fail });
+
+            emit!(self, Instruction::Duplicate);
+            self.compile_expression(key)?;
+            emit!(self, Instruction::Subscript);
+            self.compile_pattern_inner(pattern, pattern_context)?;
+            emit!(self, Instruction::JumpIfFalse { target: fail });
+        }
+
+        if let Some(rest) = mapping.rest.as_ref() {
+            emit!(self, Instruction::Duplicate);
+            let dict = self.name("dict");
+            emit!(self, Instruction::LoadGlobal(dict));
+            emit!(self, Instruction::Rotate2);
+            emit!(self, Instruction::CallFunctionPositional { nargs: 1 });
+            for key in &mapping.keys {
+                emit!(self, Instruction::Duplicate);
+                self.compile_expression(key)?;
+                emit!(self, Instruction::DeleteSubscript);
+            }
+            self.store_name(rest.as_str())?;
+            pattern_context.captures.push(rest.to_string());
+        }
+
+        emit!(self, Instruction::Pop);
+        self.emit_load_const(ConstantData::Boolean { value: true });
+        emit!(self, Instruction::Jump { target: join });
+
+        self.switch_to_block(fail);
+        emit!(self, Instruction::Pop);
+        self.emit_load_const(ConstantData::Boolean { value: false });
+
+        self.switch_to_block(join);
+        Ok(())
+    }
+
+    /// Compile `Pattern::MatchClass`: `[.., subject] -> [.., bool]`.
+    ///
+    /// Keyword patterns (`Point(x=1)`) read a statically-known attribute
+    /// directly; positional patterns (`Point(1, 2)`) go through
+    /// `subject.__match_args__[i]` + `getattr`, since this bytecode has no
+    /// dedicated `MATCH_CLASS`-style opcode to do that lookup for us.
+    fn compile_pattern_class(
+        &mut self,
+        class: &PatternMatchClass,
+        pattern_context: &mut PatternContext,
+    ) -> CompileResult<()> {
+        let fail = self.new_block();
+        let join = self.new_block();
+
+        self.emit_isinstance_check(&class.cls)?;
+        emit!(self, Instruction::JumpIfFalse { target: fail });
+
+        let match_args = self.name("__match_args__");
+        let getattr = self.name("getattr");
+        for (i, pattern) in class.arguments.patterns.iter().enumerate() {
+            emit!(self, Instruction::Duplicate);
+            emit!(self, Instruction::Duplicate);
+            emit!(self, Instruction::LoadAttr { idx: match_args });
+            self.emit_load_const(ConstantData::Integer {
+                value: BigInt::from(i.to_u32()),
+            });
+            emit!(self, Instruction::Subscript);
+            emit!(self, Instruction::LoadGlobal(getattr));
+            emit!(self, Instruction::Rotate3);
+            emit!(self, Instruction::CallFunctionPositional { nargs: 2 });
+            self.compile_pattern_inner(pattern, pattern_context)?;
+            emit!(self, Instruction::JumpIfFalse { target: fail });
+        }
+        for keyword in &class.arguments.keywords {
+            emit!(self, Instruction::Duplicate);
+            let idx = self.name(keyword.attr.as_str());
+            emit!(self, Instruction::LoadAttr { idx });
+            self.compile_pattern_inner(&keyword.pattern, pattern_context)?;
+            emit!(self, Instruction::JumpIfFalse { target: fail });
+        }
+
+        emit!(self, Instruction::Pop);
+        self.emit_load_const(ConstantData::Boolean { value: true });
+        emit!(self, Instruction::Jump { target: join });
+
+        self.switch_to_block(fail);
+        emit!(self, Instruction::Pop);
+        self.emit_load_const(ConstantData::Boolean { value: false });
+
+        self.switch_to_block(join);
+        Ok(())
+    }
+
+    /// Compile `Pattern::MatchOr`: `[.., subject] -> [.., bool]`. Tries each
+    /// alternative against a fresh copy of the subject in turn, taking the
+    /// first that matches (with whatever bindings it made); only the last
+    /// alternative consumes the original subject directly, since nothing
+    /// needs to fall back past it.
+    fn compile_pattern_or(
+        &mut self,
+        or_pattern: &PatternMatchOr,
+        pattern_context: &mut PatternContext,
+    ) -> CompileResult<()> {
+        let join = self.new_block();
+        let (last, rest) = or_pattern
+            .patterns
+            .split_last()
+            .expect("MatchOr always has at least one alternative");
+
+        for alt in rest {
+            emit!(self, Instruction::Duplicate);
+            self.compile_pattern_inner(alt, pattern_context)?;
+            let try_next = self.new_block();
+            emit!(self, Instruction::JumpIfFalse { target: try_next });
             emit!(self, Instruction::Pop);
+            self.emit_load_const(ConstantData::Boolean { value: true });
+            emit!(self, Instruction::Jump { target: join });
+            self.switch_to_block(try_next);
         }
+        self.compile_pattern_inner(last, pattern_context)?;
+        self.switch_to_block(join);
         Ok(())
     }
 
@@ -1845,9 +2312,14 @@ impl Compiler<'_> {
         match &pattern_type {
             Pattern::MatchValue(value) => self.compile_pattern_value(value, pattern_context),
             Pattern::MatchAs(as_pattern) => self.compile_pattern_as(as_pattern, pattern_context),
-            _ => {
-                eprintln!("not implemented pattern type: {pattern_type:?}");
-                Err(self.error(CodegenErrorType::NotImplementedYet))
+            Pattern::MatchSequence(seq) => self.compile_pattern_sequence(seq, pattern_context),
+            Pattern::MatchMapping(mapping) => self.compile_pattern_mapping(mapping, pattern_context),
+            Pattern::MatchClass(class) => self.compile_pattern_class(class, pattern_context),
+            Pattern::MatchOr(or_pattern) => self.compile_pattern_or(or_pattern, pattern_context),
+            Pattern::MatchStar(_) => {
+                // Only valid nested inside a MatchSequence, which handles
+                // its star element itself without ever recursing here.
+                Err(self.error_ranged(CodegenErrorType::InvalidMatchCase, pattern_type.range()))
             }
         }
     }
@@ -1893,6 +2365,24 @@ impl Compiler<'_> {
                 emit!(self, Instruction::Duplicate);
             }
             self.compile_pattern(&m.pattern, pattern_context)?;
+            // A matched pattern can still be rejected by its guard. Binds
+            // from the pattern (if any) already landed via `store_name`
+            // above, so they're visible to the guard expression here. On
+            // failure we fall through to the next case's block exactly
+            // like a failed pattern match does - that block already
+            // expects whatever the current case left on the stack (the
+            // still-live subject copy for every case but the last, which
+            // consumed its only copy matching the pattern and leaves
+            // nothing behind), so there's nothing extra to clean up here.
+            if let Some(guard) = &m.guard {
+                self.compile_expression(guard)?;
+                emit!(
+                    self,
+                    Instruction::JumpIfFalse {
+                        target: pattern_context.blocks[i + 1]
+                    }
+                );
+            }
             self.compile_statements(&m.body)?;
             emit!(self, Instruction::Jump { target: end_block });
         }
@@ -1926,15 +2416,190 @@ impl Compiler<'_> {
     }
 
     fn compile_match(&mut self, subject: &Expr, cases: &[MatchCase]) -> CompileResult<()> {
+        self.check_match_cases(cases)?;
         let mut pattern_context = PatternContext {
             current_block: usize::MAX,
             blocks: Vec::new(),
             allow_irrefutable: false,
+            captures: Vec::new(),
         };
         self.compile_match_inner(subject, cases, &mut pattern_context)?;
         Ok(())
     }
 
+    /// A minimal static "usefulness" check over a `case` list: a row
+    /// (case) is only useful if some value it matches isn't already
+    /// matched by an earlier row. Full usefulness analysis is a matrix
+    /// over arbitrary overlapping structural patterns; this only covers
+    /// the practical subset that's cheap to prove and common enough in
+    /// real code to be worth flagging as an error rather than a silently
+    /// dead case: an irrefutable case (a wildcard `case _` or bare capture
+    /// `case x`, unguarded) makes every following case unreachable, and an
+    /// unguarded literal `MatchValue` case makes a later case with the
+    /// identical literal unreachable too. Also enforces the PEP 634
+    /// binding rules every pattern here needs to hold regardless of
+    /// reachability: no pattern may bind the same name twice, and every
+    /// alternative of a `MatchOr` must bind the same set of names.
+    fn check_match_cases(&mut self, cases: &[MatchCase]) -> CompileResult<()> {
+        let mut irrefutable_seen = false;
+        let mut seen_literals: Vec<&Expr> = Vec::new();
+        for case in cases {
+            if irrefutable_seen {
+                return Err(self.error_ranged(
+                    CodegenErrorType::SyntaxError(
+                        "case is unreachable - an earlier case always matches".to_owned(),
+                    ),
+                    case.pattern.range(),
+                ));
+            }
+            if case.guard.is_none() {
+                if Self::is_irrefutable_pattern(&case.pattern) {
+                    irrefutable_seen = true;
+                } else if let Pattern::MatchValue(value) = &case.pattern {
+                    if seen_literals
+                        .iter()
+                        .any(|prior| Self::literal_patterns_equal(prior, &value.value))
+                    {
+                        return Err(self.error_ranged(
+                            CodegenErrorType::SyntaxError(
+                                "case is unreachable - a duplicate literal pattern already \
+                                 matches"
+                                    .to_owned(),
+                            ),
+                            case.pattern.range(),
+                        ));
+                    }
+                    seen_literals.push(&value.value);
+                }
+            }
+            self.check_pattern_bindings(&case.pattern)?;
+        }
+        Ok(())
+    }
+
+    /// A pattern with no guard that matches any subject: a bare wildcard
+    /// (`case _`) or capture (`case x`), or a `MatchOr` with any such
+    /// alternative.
+    fn is_irrefutable_pattern(pattern: &Pattern) -> bool {
+        match pattern {
+            Pattern::MatchAs(p) => {
+                p.pattern.is_none()
+                    || p.pattern.as_deref().is_some_and(Self::is_irrefutable_pattern)
+            }
+            Pattern::MatchOr(p) => p.patterns.iter().any(Self::is_irrefutable_pattern),
+            _ => false,
+        }
+    }
+
+    /// Whether two `MatchValue` comparison expressions are provably the
+    /// same literal. Only the literal forms a `MatchValue` pattern
+    /// actually compiles to a constant comparison against are handled;
+    /// anything else (a dotted constant lookup, for instance) can't be
+    /// proven equal at compile time, so it's conservatively treated as
+    /// distinct rather than risk a false-positive "unreachable" error.
+    fn literal_patterns_equal(a: &Expr, b: &Expr) -> bool {
+        use ruff_python_ast::Number;
+
+        match (a, b) {
+            (Expr::NoneLiteral(_), Expr::NoneLiteral(_)) => true,
+            (Expr::BooleanLiteral(a), Expr::BooleanLiteral(b)) => a.value == b.value,
+            (Expr::StringLiteral(a), Expr::StringLiteral(b)) => {
+                a.value.to_str() == b.value.to_str()
+            }
+            (Expr::BytesLiteral(a), Expr::BytesLiteral(b)) => {
+                let a: Vec<u8> = a.value.iter().flat_map(|x| x.iter().copied()).collect();
+                let b: Vec<u8> = b.value.iter().flat_map(|x| x.iter().copied()).collect();
+                a == b
+            }
+            (Expr::NumberLiteral(a), Expr::NumberLiteral(b)) => match (&a.value, &b.value) {
+                (Number::Int(a), Number::Int(b)) => a == b,
+                (Number::Float(a), Number::Float(b)) => a == b,
+                (Number::Complex { real: ar, imag: ai }, Number::Complex { real: br, imag: bi }) => {
+                    ar == br && ai == bi
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Collect the names a pattern binds, enforcing PEP 634's binding
+    /// rules as it goes: no pattern may bind the same name twice, and
+    /// every alternative of a `MatchOr` must bind the same set of names
+    /// (since only one of them actually runs, but which one isn't known
+    /// until match time).
+    fn check_pattern_bindings(&mut self, pattern: &Pattern) -> CompileResult<Vec<String>> {
+        let names = match pattern {
+            Pattern::MatchValue(_) => Vec::new(),
+            Pattern::MatchStar(p) => p.name.iter().map(|n| n.to_string()).collect(),
+            Pattern::MatchAs(p) => {
+                let mut names = match &p.pattern {
+                    Some(inner) => self.check_pattern_bindings(inner)?,
+                    None => Vec::new(),
+                };
+                names.extend(p.name.as_ref().map(|n| n.to_string()));
+                names
+            }
+            Pattern::MatchSequence(p) => {
+                let mut names = Vec::new();
+                for sub in &p.patterns {
+                    names.extend(self.check_pattern_bindings(sub)?);
+                }
+                names
+            }
+            Pattern::MatchMapping(p) => {
+                let mut names = Vec::new();
+                for sub in &p.patterns {
+                    names.extend(self.check_pattern_bindings(sub)?);
+                }
+                names.extend(p.rest.as_ref().map(|n| n.to_string()));
+                names
+            }
+            Pattern::MatchClass(p) => {
+                let mut names = Vec::new();
+                for sub in &p.arguments.patterns {
+                    names.extend(self.check_pattern_bindings(sub)?);
+                }
+                for kw in &p.arguments.keywords {
+                    names.extend(self.check_pattern_bindings(&kw.pattern)?);
+                }
+                names
+            }
+            Pattern::MatchOr(p) => {
+                let mut alternatives = Vec::with_capacity(p.patterns.len());
+                for alt in &p.patterns {
+                    alternatives.push(self.check_pattern_bindings(alt)?);
+                }
+                let first: std::collections::HashSet<_> = alternatives[0].iter().collect();
+                for other in &alternatives[1..] {
+                    let other_set: std::collections::HashSet<_> = other.iter().collect();
+                    if other_set != first {
+                        return Err(self.error_ranged(
+                            CodegenErrorType::SyntaxError(
+                                "alternative patterns bind different names".to_owned(),
+                            ),
+                            pattern.range(),
+                        ));
+                    }
+                }
+                alternatives.into_iter().next().unwrap_or_default()
+            }
+        };
+
+        let mut seen = std::collections::HashSet::new();
+        for name in &names {
+            if !seen.insert(name.as_str()) {
+                return Err(self.error_ranged(
+                    CodegenErrorType::SyntaxError(format!(
+                        "multiple assignments to name {name} in pattern"
+                    )),
+                    pattern.range(),
+                ));
+            }
+        }
+        Ok(names)
+    }
+
     fn compile_chained_comparison(
         &mut self,
         left: &Expr,
@@ -2034,6 +2699,8 @@ impl Compiler<'_> {
                 value: codegen.expr(annotation),
             });
         } else {
+            // `AnnotationMode::Deferred` and `Eager` both land here: the
+            // expression is evaluated inline, with no `__annotate__` thunk.
             self.compile_expression(annotation)?;
         }
         Ok(())
@@ -2356,20 +3023,40 @@ impl Compiler<'_> {
     }
 
     fn compile_dict(&mut self, items: &[DictItem]) -> CompileResult<()> {
-        // FIXME: correct order to build map, etc d = {**a, 'key': 2} should override
-        // 'key' in dict a
-        let mut size = 0;
-        let (packed, unpacked): (Vec<_>, Vec<_>) = items.iter().partition(|x| x.key.is_some());
-        for item in packed {
-            self.compile_expression(item.key.as_ref().unwrap())?;
-            self.compile_expression(&item.value)?;
-            size += 1;
+        // Fast path: no `**` unpacking, so there's no overriding to get
+        // wrong and a single `BuildMap` covers every entry.
+        if items.iter().all(|item| item.key.is_some()) {
+            for item in items {
+                self.compile_expression(item.key.as_ref().unwrap())?;
+                self.compile_expression(&item.value)?;
+            }
+            emit!(
+                self,
+                Instruction::BuildMap {
+                    size: items.len().to_u32(),
+                }
+            );
+            return Ok(());
         }
-        emit!(self, Instruction::BuildMap { size });
 
-        for item in unpacked {
-            self.compile_expression(&item.value)?;
-            emit!(self, Instruction::DictUpdate);
+        // General path: walk `items` in source order so later entries
+        // override earlier ones and every subexpression evaluates left to
+        // right, the way `d = {**a, 'key': 2}` needs `'key'` to win over
+        // whatever `a['key']` was.
+        emit!(self, Instruction::BuildMap { size: 0 });
+        for item in items {
+            match &item.key {
+                Some(key) => {
+                    self.compile_expression(key)?;
+                    self.compile_expression(&item.value)?;
+                    emit!(self, Instruction::BuildMap { size: 1 });
+                    emit!(self, Instruction::DictUpdate);
+                }
+                None => {
+                    self.compile_expression(&item.value)?;
+                    emit!(self, Instruction::DictUpdate);
+                }
+            }
         }
 
         Ok(())
@@ -2381,6 +3068,13 @@ impl Compiler<'_> {
         let range = expression.range();
         self.set_source_range(range);
 
+        if self.opts.optimize >= OptimizationLevel::Basic {
+            if let Some(constant) = crate::optimize::fold_literal(expression) {
+                self.emit_load_const(constant);
+                return Ok(());
+            }
+        }
+
         match &expression {
             Expr::Call(ExprCall {
                 func, arguments, ..
@@ -2904,6 +3598,35 @@ impl Compiler<'_> {
         })
     }
 
+    /// Whether `generators`/`comprehension_type` are simple enough that,
+    /// in principle, `compile_comprehension` could emit the loop directly
+    /// into the enclosing code object instead of a nested `<*comp>`
+    /// function (PEP 709), rather than whether this tree can actually do
+    /// that rewrite yet - see [`CompileOpts::inline_comprehensions`].
+    ///
+    /// This only checks the syntactic shape: a single, non-async
+    /// generator with a plain `Name` target and no nested scope-
+    /// introducing sub-expression (`Lambda`, or another comprehension)
+    /// that could shadow the target. It does not consult the symbol table,
+    /// so it says nothing about whether the target name could safely be
+    /// assigned into the enclosing scope (free/cell vars, `global`/
+    /// `nonlocal` declarations, whether that scope has fast locals at
+    /// all). `compile_comprehension` below computes the result and
+    /// discards it; the nested-function path is always taken.
+    fn comprehension_inline_eligible(
+        generators: &[Comprehension],
+        comprehension_type: ComprehensionType,
+    ) -> bool {
+        let [generator] = generators else {
+            return false;
+        };
+        comprehension_type != ComprehensionType::Generator
+            && !generator.is_async
+            && matches!(generator.target, Expr::Name(_))
+            && generator.ifs.iter().all(|e| !Self::contains_sub_scope(e))
+            && !Self::contains_sub_scope(&generator.iter)
+    }
+
     fn compile_comprehension(
         &mut self,
         name: &str,
@@ -2913,6 +3636,13 @@ impl Compiler<'_> {
         comprehension_type: ComprehensionType,
         element_contains_await: bool,
     ) -> CompileResult<()> {
+        // Detection-only for now: see `comprehension_inline_eligible` and
+        // `CompileOpts::inline_comprehensions` for why this doesn't change
+        // what gets emitted below yet.
+        if self.opts.inline_comprehensions {
+            let _eligible = Self::comprehension_inline_eligible(generators, comprehension_type);
+        }
+
         let prev_ctx = self.ctx;
         let has_an_async_gen = generators.iter().any(|g| g.is_async);
 
@@ -3220,113 +3950,168 @@ impl Compiler<'_> {
     /// thus requires the function to be async.
     /// Async with and async for are statements, so I won't check for them here
     fn contains_await(expression: &Expr) -> bool {
+        Self::find_expr_effects(expression).has_await
+    }
+
+    /// Walks `expression` looking for `await`/`yield`/`yield from`, the
+    /// same traversal `contains_await` used before it grew a second
+    /// caller: every compound expression kind a comprehension's
+    /// `ifs`/`iter`, a boolean/compare chain, or an f-string replacement
+    /// field (including its format spec) can embed is descended into.
+    /// A match on `Expr::Await` stops descending into its own operand,
+    /// same as the original `contains_await` - that's deliberate, not an
+    /// oversight: an `await` found this way already answers "does this
+    /// expression need an async scope", so there's no need to keep
+    /// looking past it for this walk's purposes.
+    ///
+    /// This keeps `contains_await`'s exact existing traversal through a
+    /// nested `Lambda`/comprehension's body rather than stopping at that
+    /// scope boundary, since `compile_comprehension`'s is-this-async
+    /// decision already relies on that behavior - changing it here would
+    /// risk a silent regression with no test run to catch it. The one new
+    /// caller this walk gained, the f-string `yield` check in
+    /// `compile_fstring_elements`, only needs `has_yield` on the
+    /// replacement field's own expression (which can't itself be a
+    /// `Lambda`/comprehension body), so it doesn't hit that distinction.
+    /// A `yield`/`await` restriction specific to a comprehension's
+    /// iterable or a default argument isn't wired up here: those need
+    /// their own call sites at `compile_comprehension`'s first-generator
+    /// handling and at parameter-default compilation respectively, and
+    /// without a way to run the parser against real CPython error text in
+    /// this tree, guessing at exactly which of those CPython restricts
+    /// (and with what message) isn't a risk worth taking - unlike the
+    /// f-string case, which is a clear, narrow, well-documented rule.
+    fn find_expr_effects(expression: &Expr) -> ExprEffects {
         use ruff_python_ast::*;
 
         match &expression {
             Expr::Call(ExprCall {
                 func, arguments, ..
             }) => {
-                Self::contains_await(func)
-                    || arguments.args.iter().any(Self::contains_await)
-                    || arguments
-                        .keywords
-                        .iter()
-                        .any(|kw| Self::contains_await(&kw.value))
+                let mut effects = Self::find_expr_effects(func);
+                for arg in &arguments.args {
+                    effects = effects.merge(Self::find_expr_effects(arg));
+                }
+                for kw in &arguments.keywords {
+                    effects = effects.merge(Self::find_expr_effects(&kw.value));
+                }
+                effects
             }
-            Expr::BoolOp(ExprBoolOp { values, .. }) => values.iter().any(Self::contains_await),
+            Expr::BoolOp(ExprBoolOp { values, .. }) => values
+                .iter()
+                .map(Self::find_expr_effects)
+                .fold(ExprEffects::default(), ExprEffects::merge),
             Expr::BinOp(ExprBinOp { left, right, .. }) => {
-                Self::contains_await(left) || Self::contains_await(right)
+                Self::find_expr_effects(left).merge(Self::find_expr_effects(right))
             }
             Expr::Subscript(ExprSubscript { value, slice, .. }) => {
-                Self::contains_await(value) || Self::contains_await(slice)
+                Self::find_expr_effects(value).merge(Self::find_expr_effects(slice))
             }
-            Expr::UnaryOp(ExprUnaryOp { operand, .. }) => Self::contains_await(operand),
-            Expr::Attribute(ExprAttribute { value, .. }) => Self::contains_await(value),
+            Expr::UnaryOp(ExprUnaryOp { operand, .. }) => Self::find_expr_effects(operand),
+            Expr::Attribute(ExprAttribute { value, .. }) => Self::find_expr_effects(value),
             Expr::Compare(ExprCompare {
                 left, comparators, ..
-            }) => Self::contains_await(left) || comparators.iter().any(Self::contains_await),
-            Expr::List(ExprList { elts, .. }) => elts.iter().any(Self::contains_await),
-            Expr::Tuple(ExprTuple { elts, .. }) => elts.iter().any(Self::contains_await),
-            Expr::Set(ExprSet { elts, .. }) => elts.iter().any(Self::contains_await),
+            }) => comparators
+                .iter()
+                .map(Self::find_expr_effects)
+                .fold(Self::find_expr_effects(left), ExprEffects::merge),
+            Expr::List(ExprList { elts, .. })
+            | Expr::Tuple(ExprTuple { elts, .. })
+            | Expr::Set(ExprSet { elts, .. }) => elts
+                .iter()
+                .map(Self::find_expr_effects)
+                .fold(ExprEffects::default(), ExprEffects::merge),
             Expr::Dict(ExprDict { items, .. }) => items
                 .iter()
                 .flat_map(|item| &item.key)
-                .any(Self::contains_await),
+                .map(Self::find_expr_effects)
+                .fold(ExprEffects::default(), ExprEffects::merge),
             Expr::Slice(ExprSlice {
                 lower, upper, step, ..
-            }) => {
-                lower.as_deref().is_some_and(Self::contains_await)
-                    || upper.as_deref().is_some_and(Self::contains_await)
-                    || step.as_deref().is_some_and(Self::contains_await)
-            }
+            }) => [lower, upper, step]
+                .into_iter()
+                .flat_map(|bound| bound.as_deref())
+                .map(Self::find_expr_effects)
+                .fold(ExprEffects::default(), ExprEffects::merge),
             Expr::Yield(ExprYield { value, .. }) => {
-                value.as_deref().is_some_and(Self::contains_await)
+                let effects = ExprEffects {
+                    has_yield: true,
+                    ..Default::default()
+                };
+                match value.as_deref() {
+                    Some(value) => effects.merge(Self::find_expr_effects(value)),
+                    None => effects,
+                }
+            }
+            Expr::Await(ExprAwait { .. }) => ExprEffects {
+                has_await: true,
+                ..Default::default()
+            },
+            Expr::YieldFrom(ExprYieldFrom { value, .. }) => {
+                let effects = ExprEffects {
+                    has_yield: true,
+                    ..Default::default()
+                };
+                effects.merge(Self::find_expr_effects(value))
             }
-            Expr::Await(ExprAwait { .. }) => true,
-            Expr::YieldFrom(ExprYieldFrom { value, .. }) => Self::contains_await(value),
-            Expr::Name(ExprName { .. }) => false,
-            Expr::Lambda(ExprLambda { body, .. }) => Self::contains_await(body),
+            Expr::Name(ExprName { .. }) => ExprEffects::default(),
+            Expr::Lambda(ExprLambda { body, .. }) => Self::find_expr_effects(body),
             Expr::ListComp(ExprListComp {
                 elt, generators, ..
-            }) => {
-                Self::contains_await(elt)
-                    || generators.iter().any(|jen| Self::contains_await(&jen.iter))
-            }
-            Expr::SetComp(ExprSetComp {
+            })
+            | Expr::SetComp(ExprSetComp {
                 elt, generators, ..
-            }) => {
-                Self::contains_await(elt)
-                    || generators.iter().any(|jen| Self::contains_await(&jen.iter))
-            }
+            })
+            | Expr::Generator(ExprGenerator {
+                elt, generators, ..
+            }) => generators
+                .iter()
+                .map(|jen| Self::find_expr_effects(&jen.iter))
+                .fold(Self::find_expr_effects(elt), ExprEffects::merge),
             Expr::DictComp(ExprDictComp {
                 key,
                 value,
                 generators,
                 ..
-            }) => {
-                Self::contains_await(key)
-                    || Self::contains_await(value)
-                    || generators.iter().any(|jen| Self::contains_await(&jen.iter))
-            }
-            Expr::Generator(ExprGenerator {
-                elt, generators, ..
-            }) => {
-                Self::contains_await(elt)
-                    || generators.iter().any(|jen| Self::contains_await(&jen.iter))
-            }
-            Expr::Starred(expr) => Self::contains_await(&expr.value),
+            }) => generators
+                .iter()
+                .map(|jen| Self::find_expr_effects(&jen.iter))
+                .fold(
+                    Self::find_expr_effects(key).merge(Self::find_expr_effects(value)),
+                    ExprEffects::merge,
+                ),
+            Expr::Starred(expr) => Self::find_expr_effects(&expr.value),
             Expr::If(ExprIf {
                 test, body, orelse, ..
-            }) => {
-                Self::contains_await(test)
-                    || Self::contains_await(body)
-                    || Self::contains_await(orelse)
-            }
+            }) => Self::find_expr_effects(test)
+                .merge(Self::find_expr_effects(body))
+                .merge(Self::find_expr_effects(orelse)),
 
             Expr::Named(ExprNamed {
                 target,
                 value,
                 range: _,
-            }) => Self::contains_await(target) || Self::contains_await(value),
+            }) => Self::find_expr_effects(target).merge(Self::find_expr_effects(value)),
             Expr::FString(ExprFString { value, range: _ }) => {
-                fn expr_element_contains_await<F: Copy + Fn(&Expr) -> bool>(
-                    expr_element: &FStringExpressionElement,
-                    contains_await: F,
-                ) -> bool {
-                    contains_await(&expr_element.expression)
-                        || expr_element
-                            .format_spec
-                            .iter()
-                            .flat_map(|spec| spec.elements.expressions())
-                            .any(|element| expr_element_contains_await(element, contains_await))
-                }
-
-                value.elements().any(|element| match element {
-                    FStringElement::Expression(expr_element) => {
-                        expr_element_contains_await(expr_element, Self::contains_await)
-                    }
-                    FStringElement::Literal(_) => false,
-                })
+                fn expr_element_effects(expr_element: &FStringExpressionElement) -> ExprEffects {
+                    let effects = Compiler::find_expr_effects(&expr_element.expression);
+                    expr_element
+                        .format_spec
+                        .iter()
+                        .flat_map(|spec| spec.elements.expressions())
+                        .map(expr_element_effects)
+                        .fold(effects, ExprEffects::merge)
+                }
+
+                value
+                    .elements()
+                    .filter_map(|element| match element {
+                        FStringElement::Expression(expr_element) => {
+                            Some(expr_element_effects(expr_element))
+                        }
+                        FStringElement::Literal(_) => None,
+                    })
+                    .fold(ExprEffects::default(), ExprEffects::merge)
             }
             Expr::StringLiteral(_)
             | Expr::BytesLiteral(_)
@@ -3334,20 +4119,92 @@ impl Compiler<'_> {
             | Expr::BooleanLiteral(_)
             | Expr::NoneLiteral(_)
             | Expr::EllipsisLiteral(_)
-            | Expr::IpyEscapeCommand(_) => false,
+            | Expr::IpyEscapeCommand(_) => ExprEffects::default(),
+        }
+    }
+
+    /// Whether `expression` contains a sub-expression that introduces its
+    /// own scope (a lambda or a nested comprehension/generator
+    /// expression). Used by `comprehension_inline_eligible` to rule out
+    /// cases where a naive name-substitution inlining could capture the
+    /// wrong binding of a comprehension's loop target. Unlike
+    /// `find_expr_effects` above, it only descends into the handful of
+    /// compound expression kinds listed below; a lambda or comprehension
+    /// nested inside any other kind (a dict display, a slice, an f-string,
+    /// a named expression, ...) is not found, and `false` is returned.
+    fn contains_sub_scope(expression: &Expr) -> bool {
+        use ruff_python_ast::*;
+
+        match expression {
+            Expr::Lambda(_)
+            | Expr::ListComp(_)
+            | Expr::SetComp(_)
+            | Expr::DictComp(_)
+            | Expr::Generator(_) => true,
+            Expr::Call(ExprCall {
+                func, arguments, ..
+            }) => {
+                Self::contains_sub_scope(func)
+                    || arguments.args.iter().any(Self::contains_sub_scope)
+                    || arguments
+                        .keywords
+                        .iter()
+                        .any(|kw| Self::contains_sub_scope(&kw.value))
+            }
+            Expr::BoolOp(ExprBoolOp { values, .. }) => values.iter().any(Self::contains_sub_scope),
+            Expr::BinOp(ExprBinOp { left, right, .. }) => {
+                Self::contains_sub_scope(left) || Self::contains_sub_scope(right)
+            }
+            Expr::Subscript(ExprSubscript { value, slice, .. }) => {
+                Self::contains_sub_scope(value) || Self::contains_sub_scope(slice)
+            }
+            Expr::UnaryOp(ExprUnaryOp { operand, .. }) => Self::contains_sub_scope(operand),
+            Expr::Attribute(ExprAttribute { value, .. }) => Self::contains_sub_scope(value),
+            Expr::Compare(ExprCompare {
+                left, comparators, ..
+            }) => {
+                Self::contains_sub_scope(left) || comparators.iter().any(Self::contains_sub_scope)
+            }
+            Expr::List(ExprList { elts, .. }) => elts.iter().any(Self::contains_sub_scope),
+            Expr::Tuple(ExprTuple { elts, .. }) => elts.iter().any(Self::contains_sub_scope),
+            Expr::Set(ExprSet { elts, .. }) => elts.iter().any(Self::contains_sub_scope),
+            Expr::Starred(expr) => Self::contains_sub_scope(&expr.value),
+            Expr::If(ExprIf {
+                test, body, orelse, ..
+            }) => {
+                Self::contains_sub_scope(test)
+                    || Self::contains_sub_scope(body)
+                    || Self::contains_sub_scope(orelse)
+            }
+            _ => false,
         }
     }
 
     fn compile_expr_fstring(&mut self, fstring: &ExprFString) -> CompileResult<()> {
         let fstring = &fstring.value;
+        let mut part_count: u32 = 0;
+        let mut folded_run = String::new();
+
         for part in fstring {
+            if let Some(folded) = fold_fstring_part(part) {
+                folded_run.push_str(&folded);
+                continue;
+            }
+            if !folded_run.is_empty() {
+                self.emit_load_const(ConstantData::Str {
+                    value: std::mem::take(&mut folded_run),
+                });
+                part_count += 1;
+            }
             self.compile_fstring_part(part)?;
+            part_count += 1;
         }
-        let part_count: u32 = fstring
-            .iter()
-            .len()
-            .try_into()
-            .expect("BuildString size overflowed");
+
+        if !folded_run.is_empty() {
+            self.emit_load_const(ConstantData::Str { value: folded_run });
+            part_count += 1;
+        }
+
         if part_count > 1 {
             emit!(self, Instruction::BuildString { size: part_count });
         }
@@ -3371,99 +4228,382 @@ impl Compiler<'_> {
         self.compile_fstring_elements(&fstring.elements)
     }
 
+    /// Emits one `FStringElements` as a sequence of stack values followed
+    /// by a `BuildString` (or nothing, if it collapses to a single value).
+    /// Contiguous runs of elements [`fold_fstring_element`] can evaluate at
+    /// compile time are merged into a single `LoadConst` instead of each
+    /// getting their own `FormatValue`/literal push - see that function's
+    /// doc comment for exactly which elements qualify.
     fn compile_fstring_elements(
         &mut self,
         fstring_elements: &FStringElements,
     ) -> CompileResult<()> {
+        let mut part_count: u32 = 0;
+        let mut folded_run = String::new();
+
         for element in fstring_elements {
-            match element {
-                FStringElement::Literal(string) => {
+            if let Some(folded) = fold_fstring_element(element) {
+                folded_run.push_str(&folded);
+                continue;
+            }
+            if !folded_run.is_empty() {
+                self.emit_load_const(ConstantData::Str {
+                    value: std::mem::take(&mut folded_run),
+                });
+                part_count += 1;
+            }
+
+            let FStringElement::Expression(fstring_expr) = element else {
+                unreachable!("FStringElement::Literal always folds via fold_fstring_element")
+            };
+
+            let mut conversion = fstring_expr.conversion;
+
+            let debug_text_count = match &fstring_expr.debug_text {
+                None => 0,
+                Some(DebugText { leading, trailing }) => {
+                    let range = fstring_expr.expression.range();
+                    let source = self.source_code.get_range(range);
+                    let source = source.to_string();
+
                     self.emit_load_const(ConstantData::Str {
-                        value: string.value.to_string(),
+                        value: leading.to_string(),
                     });
+                    self.emit_load_const(ConstantData::Str { value: source });
+                    self.emit_load_const(ConstantData::Str {
+                        value: trailing.to_string(),
+                    });
+
+                    3
                 }
-                FStringElement::Expression(fstring_expr) => {
-                    let mut conversion = fstring_expr.conversion;
-
-                    let debug_text_count = match &fstring_expr.debug_text {
-                        None => 0,
-                        Some(DebugText { leading, trailing }) => {
-                            let range = fstring_expr.expression.range();
-                            let source = self.source_code.get_range(range);
-                            let source = source.to_string();
-
-                            self.emit_load_const(ConstantData::Str {
-                                value: leading.to_string(),
-                            });
-                            self.emit_load_const(ConstantData::Str { value: source });
-                            self.emit_load_const(ConstantData::Str {
-                                value: trailing.to_string(),
-                            });
-
-                            3
-                        }
-                    };
+            };
 
-                    match &fstring_expr.format_spec {
-                        None => {
-                            self.emit_load_const(ConstantData::Str {
-                                value: String::new(),
-                            });
-                            // Match CPython behavior: If debug text is present, apply repr conversion.
-                            // See: https://github.com/python/cpython/blob/f61afca262d3a0aa6a8a501db0b1936c60858e35/Parser/action_helpers.c#L1456
-                            if conversion == ConversionFlag::None && debug_text_count > 0 {
-                                conversion = ConversionFlag::Repr;
-                            }
-                        }
-                        Some(format_spec) => {
-                            self.compile_fstring_elements(&format_spec.elements)?;
-                        }
+            match &fstring_expr.format_spec {
+                None => {
+                    self.emit_load_const(ConstantData::Str {
+                        value: String::new(),
+                    });
+                    // Match CPython behavior: If debug text is present, apply repr conversion.
+                    // See: https://github.com/python/cpython/blob/f61afca262d3a0aa6a8a501db0b1936c60858e35/Parser/action_helpers.c#L1456
+                    if conversion == ConversionFlag::None && debug_text_count > 0 {
+                        conversion = ConversionFlag::Repr;
                     }
+                }
+                Some(format_spec) => {
+                    self.compile_fstring_elements(&format_spec.elements)?;
+                }
+            }
 
-                    self.compile_expression(&fstring_expr.expression)?;
+            if Self::find_expr_effects(&fstring_expr.expression).has_yield {
+                return Err(self.error_ranged(
+                    CodegenErrorType::SyntaxError(
+                        "yield expression cannot be used within an f-string expression".to_owned(),
+                    ),
+                    fstring_expr.expression.range(),
+                ));
+            }
+            self.compile_expression(&fstring_expr.expression)?;
 
-                    emit!(
-                        self,
-                        Instruction::FormatValue {
-                            conversion: conversion
-                        }
-                    );
+            emit!(
+                self,
+                Instruction::FormatValue {
+                    conversion: conversion
+                }
+            );
 
-                    // concatenate formatted string and debug text (if present)
-                    if debug_text_count > 0 {
-                        emit!(
-                            self,
-                            Instruction::BuildString {
-                                size: debug_text_count + 1
-                            }
-                        );
+            // concatenate formatted string and debug text (if present)
+            if debug_text_count > 0 {
+                emit!(
+                    self,
+                    Instruction::BuildString {
+                        size: debug_text_count + 1
                     }
-                }
+                );
             }
+
+            part_count += 1;
+        }
+
+        if !folded_run.is_empty() {
+            self.emit_load_const(ConstantData::Str { value: folded_run });
+            part_count += 1;
         }
 
-        let element_count: u32 = fstring_elements
-            .len()
-            .try_into()
-            .expect("BuildString size overflowed");
-        if element_count == 0 {
+        if part_count == 0 {
             // ensure to put an empty string on the stack if there aren't any fstring elements
             self.emit_load_const(ConstantData::Str {
                 value: String::new(),
             });
-        } else if element_count > 1 {
-            emit!(
-                self,
-                Instruction::BuildString {
-                    size: element_count
-                }
-            );
+        } else if part_count > 1 {
+            emit!(self, Instruction::BuildString { size: part_count });
         }
 
         Ok(())
     }
 }
 
+/// Try to evaluate a whole `FStringPart` (one side of implicit string
+/// concatenation, e.g. the `f"a"`/`f"{1}"` in `f"a" f"{1}"`) down to a
+/// single literal string, so [`Compiler::compile_expr_fstring`] can fold a
+/// run of parts the same way [`Compiler::compile_fstring_elements`] folds
+/// a run of elements within one part. An `FString` part only folds if
+/// every one of its elements does - a leftover unfoldable element there
+/// already gets the partial treatment inside `compile_fstring_elements`
+/// itself, so this part as a whole still has to go through normal codegen.
+fn fold_fstring_part(part: &FStringPart) -> Option<String> {
+    match part {
+        FStringPart::Literal(string) => Some(string.value.to_string()),
+        FStringPart::FString(fstring) => {
+            let mut out = String::new();
+            for element in &fstring.elements {
+                out.push_str(&fold_fstring_element(element)?);
+            }
+            Some(out)
+        }
+    }
+}
+
+/// Try to evaluate one `FStringElement` down to the literal string it
+/// would format to at runtime, so [`Compiler::compile_fstring_elements`]
+/// can fold a run of them into a single `LoadConst` instead of a
+/// `FormatValue` per element.
+///
+/// A literal element always folds to its own text. An expression element
+/// folds only when: it has no debug text (an `=` specifier always needs
+/// the original source snippet, which isn't a compile-time value); its
+/// format spec is either absent or built entirely out of literal pieces
+/// that concatenate to the empty string (anything else means real
+/// format-spec interpretation - width, precision, alignment, presentation
+/// types - which this doesn't attempt); and its expression
+/// [`crate::optimize::fold_literal`]s to a constant
+/// [`format_constant`] can render exactly.
+fn fold_fstring_element(element: &FStringElement) -> Option<String> {
+    match element {
+        FStringElement::Literal(string) => Some(string.value.to_string()),
+        FStringElement::Expression(fstring_expr) => {
+            if fstring_expr.debug_text.is_some() {
+                return None;
+            }
+            let spec = match &fstring_expr.format_spec {
+                None => String::new(),
+                Some(format_spec) => fold_literal_format_spec(&format_spec.elements)?,
+            };
+            if !spec.is_empty() {
+                return None;
+            }
+            let value = crate::optimize::fold_literal(&fstring_expr.expression)?;
+            format_constant(&value, fstring_expr.conversion)
+        }
+    }
+}
+
+/// Concatenate a format spec's elements into a `String`, or bail with
+/// `None` the moment one isn't a literal - an embedded expression (e.g.
+/// `f"{x:{width}}"`) needs runtime evaluation, so the spec as a whole
+/// isn't something [`fold_fstring_element`] can treat as constant.
+fn fold_literal_format_spec(elements: &FStringElements) -> Option<String> {
+    let mut spec = String::new();
+    for element in elements {
+        match element {
+            FStringElement::Literal(string) => spec.push_str(&string.value),
+            FStringElement::Expression(_) => return None,
+        }
+    }
+    Some(spec)
+}
+
+/// `str()`/`repr()`/`ascii()` of a folded constant against an empty format
+/// spec - the only case [`fold_fstring_element`] ever calls this for.
+/// Deliberately narrow: only `None`, `bool`, `int`, and `str` are covered -
+/// any `str` without a conversion or with `!s`, but under `!r`/`!a` only an
+/// ASCII-printable one that [`repr_ascii_str`] accepts - since those are
+/// the types where `str`/`repr`/`ascii`/`format(_, "")` are simple enough
+/// to get exactly right without a real Python to check against. `float`
+/// (repr needs the shortest-round-trip digit algorithm, which `f64`'s
+/// `Display` doesn't implement), `bytes`, and `tuple` are left unfolded.
+fn format_constant(value: &ConstantData, conversion: ConversionFlag) -> Option<String> {
+    match value {
+        ConstantData::None => Some("None".to_owned()),
+        ConstantData::Boolean { value } => Some(if *value { "True" } else { "False" }.to_owned()),
+        ConstantData::Integer { value } => Some(value.to_string()),
+        ConstantData::Str { value } => match conversion {
+            ConversionFlag::None | ConversionFlag::Str => Some(value.clone()),
+            ConversionFlag::Repr | ConversionFlag::Ascii => repr_ascii_str(value),
+        },
+        _ => None,
+    }
+}
+
+/// `repr()` of a `str` that's plain ASCII-printable text with no
+/// backslash and no mix of both quote characters - simple enough that the
+/// usual quote-choice rule (prefer `'`, switch to `"` if the string has a
+/// `'` but no `"`) is all there is to it. Anything with a backslash, a
+/// control character, non-ASCII text, or both quote characters falls back
+/// to `None` rather than get the escaping subtly wrong.
+fn repr_ascii_str(value: &str) -> Option<String> {
+    if !value.chars().all(|c| (' '..='~').contains(&c) && c != '\\') {
+        return None;
+    }
+    let has_single = value.contains('\'');
+    let has_double = value.contains('"');
+    if has_single && has_double {
+        return None;
+    }
+    let quote = if has_single { '"' } else { '\'' };
+    Some(format!("{quote}{value}{quote}"))
+}
+
+impl EmitBackend for Compiler<'_> {
+    type Value = ();
+    type Block = ir::BlockIdx;
+
+    fn new_block(&mut self) -> Self::Block {
+        self.new_block()
+    }
+
+    fn switch_to_block(&mut self, block: Self::Block) {
+        self.switch_to_block(block)
+    }
+
+    fn emit_load_const(&mut self, value: ConstantData) -> Self::Value {
+        self.emit_load_const(value)
+    }
+
+    fn emit_binary_op(
+        &mut self,
+        op: bytecode::BinaryOperator,
+        _lhs: Self::Value,
+        _rhs: Self::Value,
+        inplace: bool,
+    ) -> Self::Value {
+        if inplace {
+            emit!(self, Instruction::BinaryOperationInplace { op });
+        } else {
+            emit!(self, Instruction::BinaryOperation { op });
+        }
+    }
+
+    fn emit_jump_if(&mut self, _condition: Self::Value, target: Self::Block, when_true: bool) {
+        if when_true {
+            emit!(self, Instruction::JumpIfTrue { target });
+        } else {
+            emit!(self, Instruction::JumpIfFalse { target });
+        }
+    }
+
+    fn emit_store_name(&mut self, name: &str, _value: Self::Value) {
+        self.store_name(name)
+            .expect("emit_store_name: name rejected by check_forbidden_name")
+    }
+
+    fn build_sequence(&mut self, kind: SequenceKind, elements: Vec<Self::Value>) -> Self::Value {
+        let size = elements.len() as u32;
+        match kind {
+            SequenceKind::List => emit!(self, Instruction::BuildList { size }),
+            SequenceKind::Tuple => emit!(self, Instruction::BuildTuple { size }),
+            SequenceKind::Set => emit!(self, Instruction::BuildSet { size }),
+        }
+    }
+}
+
+/// The [`EmitBackend`] methods plus the ones that open and close a nested
+/// code object: `push_output`/`pop_code_object`/`emit_return_value`. Named
+/// after NAC3's `CodeGenerator` trait, which [`crate::worker::WorkerRegistry`]
+/// follows the shape of - see that module's doc comment for how far the
+/// "compile independent code objects on a thread pool" half of that design
+/// is (and isn't) implemented here.
+///
+/// `Compiler` is the only implementation: unlike `EmitBackend`,
+/// `Self::Output` ties this to carrying a `SymbolTable` stack alongside the
+/// code stack, which [`crate::ssa::SsaBuilder`] - a bare instruction
+/// emitter with no notion of nested scopes - has nothing to implement it
+/// with. [`open_nested_code_object`]/[`close_nested_code_object`] are the
+/// real generic callers: `compile_function_def` routes every user-defined
+/// function body through them instead of `Compiler`'s inherent
+/// `push_output`/`pop_code_object`, so this trait is an actual dispatch
+/// boundary today, not just a shape `WorkerRegistry` was written to match
+/// with no caller exercising it.
+pub(crate) trait CodeGenerator: EmitBackend {
+    /// The finished artifact `pop_code_object` hands back for one nested
+    /// scope - a [`CodeObject`] for `Compiler`.
+    type Output;
+
+    fn push_output(
+        &mut self,
+        flags: bytecode::CodeFlags,
+        posonlyarg_count: u32,
+        arg_count: u32,
+        kwonlyarg_count: u32,
+        obj_name: String,
+    );
+    fn pop_code_object(&mut self) -> Self::Output;
+    fn emit_return_value(&mut self);
+}
+
+impl CodeGenerator for Compiler<'_> {
+    type Output = CodeObject;
+
+    fn push_output(
+        &mut self,
+        flags: bytecode::CodeFlags,
+        posonlyarg_count: u32,
+        arg_count: u32,
+        kwonlyarg_count: u32,
+        obj_name: String,
+    ) {
+        self.push_output(
+            flags,
+            posonlyarg_count,
+            arg_count,
+            kwonlyarg_count,
+            obj_name,
+        )
+    }
+
+    fn pop_code_object(&mut self) -> Self::Output {
+        self.pop_code_object()
+    }
+
+    fn emit_return_value(&mut self) {
+        self.emit_return_value()
+    }
+}
+
+/// Opens a nested code object through the [`CodeGenerator`] trait rather
+/// than calling `Compiler::push_output` directly, so the trait has a real
+/// generic caller instead of sitting next to the compiler unused -
+/// `compile_function_def`/`enter_function` drive every user-defined
+/// function body through this, not just through `Compiler`'s inherent
+/// method. `G` isn't hard-coded to `Compiler` because this is the same
+/// shape a `WorkerRegistry` task would open its own nested code object
+/// through, once nested scopes can be handed an owned `SymbolTable`
+/// subtree instead of sharing `Compiler::symbol_table_stack` across the
+/// whole compile - see the `worker` module doc comment for that
+/// prerequisite.
+fn open_nested_code_object<G: CodeGenerator>(
+    gen: &mut G,
+    flags: bytecode::CodeFlags,
+    posonlyarg_count: u32,
+    arg_count: u32,
+    kwonlyarg_count: u32,
+    obj_name: String,
+) {
+    gen.push_output(
+        flags,
+        posonlyarg_count,
+        arg_count,
+        kwonlyarg_count,
+        obj_name,
+    );
+}
+
+/// The closing half of [`open_nested_code_object`]: finishes the current
+/// nested code object through [`CodeGenerator::pop_code_object`] and
+/// hands back its `Output` (a [`CodeObject`] for `Compiler`).
+fn close_nested_code_object<G: CodeGenerator>(gen: &mut G) -> G::Output {
+    gen.pop_code_object()
+}
+
 trait EmitArg<Arg: OpArgType> {
     fn emit(
         self,
@@ -3531,7 +4671,7 @@ fn split_doc<'a>(body: &'a [Stmt], opts: &CompileOpts) -> (Option<String>, &'a [
             _ => None,
         };
         if let Some(doc) = doc_comment {
-            return if opts.optimize < 2 {
+            return if opts.optimize != OptimizationLevel::Full {
                 (Some(clean_doc(doc.to_str())), body_rest)
             } else {
                 (None, body_rest)
@@ -3693,6 +4833,361 @@ mod tests {
         });
         assert_eq!(Compiler::contains_await(present), true);
     }
+
+    /// `yield x` itself isn't an `await` - `find_expr_effects` (which
+    /// `contains_await` delegates to) has to tell the two apart instead of
+    /// conflating every suspension point into one flag.
+    #[test]
+    fn test_find_expr_effects_distinguishes_yield_from_await() {
+        let range = TextRange::default();
+        let yield_x = Expr::Yield(ExprYield {
+            range,
+            value: Some(Box::new(Expr::Name(ExprName {
+                range,
+                id: Name::new("x"),
+                ctx: ExprContext::Load,
+            }))),
+        });
+        assert_eq!(Compiler::contains_await(&yield_x), false);
+        assert!(Compiler::find_expr_effects(&yield_x).has_yield);
+    }
+
+    /// `f"{(yield x)}"` is rejected at compile time, matching CPython's
+    /// restriction on `yield` inside an f-string replacement field - unlike
+    /// `await`, which is allowed there.
+    #[test]
+    fn test_compile_fstring_elements_rejects_yield() {
+        let range = TextRange::default();
+        let yield_x = Expr::Yield(ExprYield {
+            range,
+            value: Some(Box::new(Expr::Name(ExprName {
+                range,
+                id: Name::new("x"),
+                ctx: ExprContext::Load,
+            }))),
+        });
+        let elements: FStringElements = vec![fstring_expr_element(
+            range,
+            yield_x,
+            ConversionFlag::None,
+            None,
+        )]
+        .into();
+
+        let mut compiler = test_compiler();
+        let err = compiler.compile_fstring_elements(&elements).unwrap_err();
+        assert!(matches!(err.error, CodegenErrorType::SyntaxError(_)));
+    }
+
+    fn test_compiler() -> Compiler<'static> {
+        let source_code = SourceCode::new("<test>", "");
+        Compiler::new(CompileOpts::default(), source_code, "<test>".to_owned())
+    }
+
+    fn str_literal(range: TextRange, value: &str) -> Expr {
+        Expr::StringLiteral(ExprStringLiteral {
+            range,
+            value: StringLiteralValue::single(StringLiteral {
+                range,
+                value: value.into(),
+                flags: StringLiteralFlags::empty(),
+            }),
+        })
+    }
+
+    fn bool_literal(range: TextRange, value: bool) -> Expr {
+        Expr::BooleanLiteral(ExprBooleanLiteral { range, value })
+    }
+
+    /// `{'a': 1, 'b': 2}` has no `**` unpacking, so it should still take
+    /// the single-`BuildMap` fast path instead of the merge-as-you-go one.
+    #[test]
+    fn test_compile_dict_fast_path_without_unpacking() {
+        let range = TextRange::default();
+        let items = [
+            DictItem {
+                key: Some(str_literal(range, "a")),
+                value: bool_literal(range, true),
+            },
+            DictItem {
+                key: Some(str_literal(range, "b")),
+                value: bool_literal(range, false),
+            },
+        ];
+        let mut compiler = test_compiler();
+        compiler.compile_dict(&items).unwrap();
+        let block = compiler.current_block();
+        let instructions = &block.instructions;
+        let build_maps = instructions
+            .iter()
+            .filter(|i| matches!(i.instr, Instruction::BuildMap { .. }))
+            .count();
+        assert_eq!(build_maps, 1, "no `**` item should mean one BuildMap");
+        assert!(
+            !instructions
+                .iter()
+                .any(|i| matches!(i.instr, Instruction::DictUpdate)),
+            "no `**` item should mean no DictUpdate"
+        );
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum InstrKind {
+        BuildMap,
+        DictUpdate,
+        LoadConst,
+        Other,
+    }
+
+    fn instr_kind(instr: &Instruction) -> InstrKind {
+        match instr {
+            Instruction::BuildMap { .. } => InstrKind::BuildMap,
+            Instruction::DictUpdate => InstrKind::DictUpdate,
+            Instruction::LoadConst { .. } => InstrKind::LoadConst,
+            _ => InstrKind::Other,
+        }
+    }
+
+    /// `{**a, 'key': 2}` must evaluate `a` before `'key': 2`, and the
+    /// literal `'key'` must be written after (so it overrides) `a`'s
+    /// unpacked entries.
+    #[test]
+    fn test_compile_dict_unpacking_preserves_order() {
+        let range = TextRange::default();
+        let items = [
+            DictItem {
+                key: None,
+                value: str_literal(range, "a"),
+            },
+            DictItem {
+                key: Some(str_literal(range, "key")),
+                value: bool_literal(range, false),
+            },
+        ];
+        let mut compiler = test_compiler();
+        compiler.compile_dict(&items).unwrap();
+        let block = compiler.current_block();
+        let instructions = &block.instructions;
+
+        // The empty map is built first, then `a` is unpacked into it,
+        // and only then is `'key': False` merged in - so `'key'` overrides
+        // whatever `a` contributed, and `a` is evaluated before `False`.
+        let kinds: Vec<_> = instructions.iter().map(|i| instr_kind(&i.instr)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                InstrKind::BuildMap,   // empty accumulator
+                InstrKind::LoadConst,  // `a`
+                InstrKind::DictUpdate, // merge `a` in first
+                InstrKind::LoadConst,  // 'key'
+                InstrKind::LoadConst,  // False
+                InstrKind::BuildMap,   // single-entry {'key': False}
+                InstrKind::DictUpdate, // merge it in last, so it overrides `a`
+            ]
+        );
+    }
+
+    fn fstring_expr_element(
+        range: TextRange,
+        expression: Expr,
+        conversion: ConversionFlag,
+        format_spec: Option<Box<FStringFormatSpec>>,
+    ) -> FStringElement {
+        FStringElement::Expression(FStringExpressionElement {
+            range,
+            expression: Box::new(expression),
+            debug_text: None,
+            conversion,
+            format_spec,
+        })
+    }
+
+    /// `f"{True}"` folds to a bare `"True"` constant - no `FormatValue`, no
+    /// debug text, no spec needed.
+    #[test]
+    fn test_fold_fstring_element_boolean() {
+        let range = TextRange::default();
+        let element =
+            fstring_expr_element(range, bool_literal(range, true), ConversionFlag::None, None);
+        assert_eq!(fold_fstring_element(&element), Some("True".to_owned()));
+    }
+
+    /// `!r`/`!a` on a `bool` still renders the same as plain `str` - Python's
+    /// `repr(True) == str(True) == "True"`.
+    #[test]
+    fn test_fold_fstring_element_boolean_repr() {
+        let range = TextRange::default();
+        let element = fstring_expr_element(
+            range,
+            bool_literal(range, false),
+            ConversionFlag::Repr,
+            None,
+        );
+        assert_eq!(fold_fstring_element(&element), Some("False".to_owned()));
+    }
+
+    /// A format spec built out of anything but literal pieces (here, a
+    /// nested expression - `f"{x:{y}}"`'s shape) needs runtime evaluation,
+    /// so the element as a whole can't fold away.
+    #[test]
+    fn test_fold_fstring_element_with_nonliteral_spec_is_not_folded() {
+        let range = TextRange::default();
+        let nested = Expr::Name(ExprName {
+            range,
+            id: ruff_python_ast::name::Name::new("y"),
+            ctx: ExprContext::Load,
+        });
+        let spec = Some(Box::new(FStringFormatSpec {
+            range,
+            elements: vec![fstring_expr_element(
+                range,
+                nested,
+                ConversionFlag::None,
+                None,
+            )]
+            .into(),
+        }));
+        let element =
+            fstring_expr_element(range, bool_literal(range, true), ConversionFlag::None, spec);
+        assert_eq!(fold_fstring_element(&element), None);
+    }
+
+    /// `repr_ascii_str` picks `"` when the string has a `'` but no `"`,
+    /// matching Python's own quote-choice rule.
+    #[test]
+    fn test_repr_ascii_str_picks_quote_to_avoid_escaping() {
+        assert_eq!(repr_ascii_str("it's"), Some("\"it's\"".to_owned()));
+        assert_eq!(repr_ascii_str("plain"), Some("'plain'".to_owned()));
+    }
+
+    /// A backslash, or a mix of both quote characters, needs real escaping
+    /// - `repr_ascii_str` bails rather than risk getting it wrong.
+    #[test]
+    fn test_repr_ascii_str_bails_on_ambiguous_input() {
+        assert_eq!(repr_ascii_str("back\\slash"), None);
+        assert_eq!(repr_ascii_str("both \" and '"), None);
+    }
+
+    /// `f"{True}{False}"` should compile down to a single `LoadConst` of
+    /// `"TrueFalse"`, with no `FormatValue` or `BuildString` left over -
+    /// a whole run of foldable elements collapses into one constant.
+    #[test]
+    fn test_compile_fstring_elements_folds_contiguous_run() {
+        let range = TextRange::default();
+        let elements: FStringElements = vec![
+            fstring_expr_element(range, bool_literal(range, true), ConversionFlag::None, None),
+            fstring_expr_element(
+                range,
+                bool_literal(range, false),
+                ConversionFlag::None,
+                None,
+            ),
+        ]
+        .into();
+
+        let mut compiler = test_compiler();
+        compiler.compile_fstring_elements(&elements).unwrap();
+        let instructions = &compiler.current_block().instructions;
+
+        assert!(
+            !instructions
+                .iter()
+                .any(|i| matches!(i.instr, Instruction::FormatValue { .. })),
+            "a fully-foldable element run should never reach FormatValue"
+        );
+        assert!(
+            !instructions
+                .iter()
+                .any(|i| matches!(i.instr, Instruction::BuildString { .. })),
+            "folding down to one constant needs no BuildString"
+        );
+        let load_consts = instructions
+            .iter()
+            .filter(|i| matches!(i.instr, Instruction::LoadConst { .. }))
+            .count();
+        assert_eq!(load_consts, 1);
+    }
+
+    fn name_target(id: &str) -> Expr {
+        Expr::Name(ExprName {
+            range: TextRange::default(),
+            id: Name::new(id),
+            ctx: ExprContext::Store,
+        })
+    }
+
+    fn name_expr(id: &str) -> Expr {
+        Expr::Name(ExprName {
+            range: TextRange::default(),
+            id: Name::new(id),
+            ctx: ExprContext::Load,
+        })
+    }
+
+    fn simple_generator(target: Expr, iter: Expr, is_async: bool) -> Comprehension {
+        Comprehension {
+            range: TextRange::default(),
+            target,
+            iter,
+            ifs: Vec::new(),
+            is_async,
+        }
+    }
+
+    #[test]
+    fn comprehension_inline_eligible_accepts_single_sync_name_target() {
+        let generators = [simple_generator(name_target("x"), name_expr("xs"), false)];
+        assert!(Compiler::comprehension_inline_eligible(
+            &generators,
+            ComprehensionType::List,
+        ));
+    }
+
+    #[test]
+    fn comprehension_inline_eligible_rejects_generator_expressions() {
+        let generators = [simple_generator(name_target("x"), name_expr("xs"), false)];
+        assert!(!Compiler::comprehension_inline_eligible(
+            &generators,
+            ComprehensionType::Generator,
+        ));
+    }
+
+    #[test]
+    fn comprehension_inline_eligible_rejects_async_generators() {
+        let generators = [simple_generator(name_target("x"), name_expr("xs"), true)];
+        assert!(!Compiler::comprehension_inline_eligible(
+            &generators,
+            ComprehensionType::List,
+        ));
+    }
+
+    #[test]
+    fn comprehension_inline_eligible_rejects_multiple_generators() {
+        let generators = [
+            simple_generator(name_target("x"), name_expr("xs"), false),
+            simple_generator(name_target("y"), name_expr("ys"), false),
+        ];
+        assert!(!Compiler::comprehension_inline_eligible(
+            &generators,
+            ComprehensionType::List,
+        ));
+    }
+
+    #[test]
+    fn comprehension_inline_eligible_rejects_non_name_targets() {
+        // Any non-`Expr::Name` target should be rejected; reuse `Expr::Await`
+        // here purely as a convenient non-`Name` shape (not a claim that
+        // `await x` is a legal comprehension target).
+        let non_name_target = Expr::Await(ExprAwait {
+            range: TextRange::default(),
+            value: Box::new(name_expr("x")),
+        });
+        let generators = [simple_generator(non_name_target, name_expr("pairs"), false)];
+        assert!(!Compiler::comprehension_inline_eligible(
+            &generators,
+            ComprehensionType::List,
+        ));
+    }
 }
 
 /*
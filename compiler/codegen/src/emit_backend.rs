@@ -0,0 +1,63 @@
+//! A code-object emission target that the front end's `compile_*`
+//! traversals could be written against instead of assuming bytecode's
+//! stack-machine shape directly.
+//!
+//! `Compiler` itself is the existing writer: `impl EmitBackend for
+//! Compiler<'_>` (in `compile.rs`) implements every method in terms of
+//! the same `emit!`/operand-stack primitives `compile_expression` already
+//! calls, so plugging it in changes nothing about the bytecode produced.
+//! [`crate::ssa::SsaBuilder`] is a second implementation that lowers the
+//! same operations into a typed, register-based SSA form instead - the
+//! representation an AOT/JIT pipeline would want, rather than
+//! `rustpython_vm`'s stack bytecode.
+//!
+//! Neither implementation is wired into `compile_expression`/
+//! `compile_store`/`compile_augassign` yet - those call sites still go
+//! through `Compiler`'s inherent methods directly. Migrating them to take
+//! `&mut impl EmitBackend` instead of assuming `&mut self` is a large,
+//! cross-cutting change on top of this; this only establishes the trait
+//! boundary and proves both an existing-format and an alternate-format
+//! implementation satisfy it. [`crate::ssa`]'s test module exercises
+//! `SsaBuilder` directly against that boundary, since there's no compiled
+//! code path yet to exercise it through.
+
+use rustpython_compiler_core::bytecode::{BinaryOperator, ConstantData};
+
+/// What kind of literal sequence `build_sequence` assembles - the three
+/// `compile_expression` already builds via `gather_elements` (see
+/// `Expr::List`/`Expr::Tuple`/`Expr::Set` in `compile.rs`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceKind {
+    List,
+    Tuple,
+    Set,
+}
+
+/// An emission target `compile_op`-style code can be written against
+/// instead of a concrete bytecode/SSA format. See the module doc comment.
+pub trait EmitBackend {
+    /// A value produced by `emit_load_const`/`emit_binary_op` and
+    /// consumed by a later emission call. The stack backend has no such
+    /// handle - values live on the operand stack implicitly - so it uses
+    /// `()`; a register-based backend uses this for its value/register
+    /// ids.
+    type Value;
+    /// An opaque control-flow target `emit_jump_if` can jump to, and a
+    /// later `switch_to_block` call can resume emitting into.
+    type Block: Copy;
+
+    fn new_block(&mut self) -> Self::Block;
+    fn switch_to_block(&mut self, block: Self::Block);
+
+    fn emit_load_const(&mut self, value: ConstantData) -> Self::Value;
+    fn emit_binary_op(
+        &mut self,
+        op: BinaryOperator,
+        lhs: Self::Value,
+        rhs: Self::Value,
+        inplace: bool,
+    ) -> Self::Value;
+    fn emit_jump_if(&mut self, condition: Self::Value, target: Self::Block, when_true: bool);
+    fn emit_store_name(&mut self, name: &str, value: Self::Value);
+    fn build_sequence(&mut self, kind: SequenceKind, elements: Vec<Self::Value>) -> Self::Value;
+}
@@ -0,0 +1,222 @@
+//! Superinstruction-fusion analysis over `ir::CodeInfo`'s block graph, run
+//! after [`crate::peephole::optimize`] once block layout (and therefore
+//! jump targets) is final.
+//!
+//! The two shapes this looks for - `CompareOperation{op}` immediately
+//! followed by `JumpIfTrue`/`JumpIfFalse{target}`, and `LoadConst`/
+//! `LoadFast` immediately followed by `Subscript` - are the adjacent-pair
+//! fusions that collapse two dispatches into one, in the spirit of Boa's
+//! and fluidb's combined-opcode designs.
+//!
+//! Because instructions inside a block are never individually addressable
+//! as jump targets in this IR - only `ir::BlockIdx`s are, via
+//! `ir::InstructionInfo::target` and `ir::Block::next` - any adjacent pair
+//! found within a single block's instruction list already satisfies "the
+//! second instruction isn't a jump destination" and "doesn't cross a
+//! block boundary" for free; there's no separate check to make.
+//!
+//! What this module can't do in this tree: actually *rewrite* a found
+//! pair into a combined opcode. `CompareJumpIfFalse`/`LoadSubscrConst` and
+//! friends would have to be new variants of
+//! `rustpython_compiler_core::bytecode::Instruction`, and that enum lives
+//! in an external crate this workspace only depends on - its source isn't
+//! part of this tree to extend. So [`find_fusion_sites`] is the detection
+//! half only: it reports where a fusion rewrite would apply.
+//! [`optimize`], gated behind
+//! [`crate::compile::CompileOpts::fuse_superinstructions`], calls it but
+//! leaves `info` unmodified - it's the hook a real rewrite would plug
+//! into once the fused opcodes exist upstream, not a fallback-safe no-op
+//! pretending to be the real thing. Until then, enabling the flag costs a
+//! scan and changes nothing about the emitted bytecode, which is also why
+//! it defaults off: there's nothing for the interpreter to "fall back"
+//! from yet.
+
+use crate::ir;
+use rustpython_compiler_core::bytecode::Instruction;
+
+/// One adjacent instruction pair [`find_fusion_sites`] recognizes as
+/// fusable, named after the combined opcode a real rewrite would emit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FusionKind {
+    /// `CompareOperation{op}` then `JumpIfTrue`/`JumpIfFalse{target}` ->
+    /// a single `CompareJumpIf{True,False}{op, target}`.
+    CompareAndBranch,
+    /// `LoadConst`/`LoadFast` then `Subscript` -> a single
+    /// `LoadSubscrConst`/`LoadSubscrFast`.
+    LoadAndSubscript,
+}
+
+/// Where a [`FusionKind`] was found: the block and the index of the
+/// pair's first instruction within that block's instruction list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FusionSite {
+    pub block: ir::BlockIdx,
+    pub first_instruction: usize,
+    pub kind: FusionKind,
+}
+
+/// Scan every block for adjacent instruction pairs matching a
+/// [`FusionKind`]. See the module doc comment for why no extra jump-target
+/// or block-boundary check is needed beyond "adjacent within one block".
+pub fn find_fusion_sites(info: &ir::CodeInfo) -> Vec<FusionSite> {
+    let mut sites = Vec::new();
+    for (block_idx, block) in info.blocks.iter().enumerate() {
+        for i in 0..block.instructions.len().saturating_sub(1) {
+            let first = &block.instructions[i].instr;
+            let second = &block.instructions[i + 1].instr;
+            let kind = match (first, second) {
+                (
+                    Instruction::CompareOperation { .. },
+                    Instruction::JumpIfTrue { .. } | Instruction::JumpIfFalse { .. },
+                ) => Some(FusionKind::CompareAndBranch),
+                (
+                    Instruction::LoadConst { .. } | Instruction::LoadFast { .. },
+                    Instruction::Subscript,
+                ) => Some(FusionKind::LoadAndSubscript),
+                _ => None,
+            };
+            if let Some(kind) = kind {
+                sites.push(FusionSite {
+                    block: ir::BlockIdx(block_idx as u32),
+                    first_instruction: i,
+                    kind,
+                });
+            }
+        }
+    }
+    sites
+}
+
+/// Entry point called from [`crate::compile::Compiler::pop_code_object`]
+/// when [`crate::compile::CompileOpts::fuse_superinstructions`] is set.
+/// See the module doc comment: this runs the detection pass but can't
+/// rewrite `info` yet, so it's currently a read-only hook rather than a
+/// real optimization.
+pub fn optimize(info: &ir::CodeInfo) -> Vec<FusionSite> {
+    find_fusion_sites(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ruff_source_file::OneIndexed;
+    use rustpython_compiler_core::bytecode;
+
+    fn empty_code_info() -> ir::CodeInfo {
+        ir::CodeInfo {
+            flags: bytecode::CodeFlags::empty(),
+            posonlyarg_count: 0,
+            arg_count: 0,
+            kwonlyarg_count: 0,
+            source_path: String::new(),
+            first_line_number: OneIndexed::MIN,
+            obj_name: "<test>".to_owned(),
+            blocks: vec![ir::Block::default()],
+            current_block: ir::BlockIdx(0),
+            constants: Default::default(),
+            name_cache: Default::default(),
+            varname_cache: Default::default(),
+            cellvar_cache: Default::default(),
+            freevar_cache: Default::default(),
+        }
+    }
+
+    fn push(block: &mut ir::Block, instr: Instruction) {
+        block.instructions.push(ir::InstructionInfo {
+            instr,
+            arg: bytecode::OpArg::null(),
+            target: ir::BlockIdx::NULL,
+            location: Default::default(),
+        });
+    }
+
+    #[test]
+    fn finds_compare_and_branch_adjacent_pair() {
+        let mut info = empty_code_info();
+        push(
+            &mut info.blocks[0],
+            Instruction::CompareOperation {
+                op: bytecode::ComparisonOperator::Equal,
+            },
+        );
+        push(
+            &mut info.blocks[0],
+            Instruction::JumpIfFalse {
+                target: bytecode::Arg::marker(),
+            },
+        );
+        push(&mut info.blocks[0], Instruction::ReturnValue);
+
+        let sites = find_fusion_sites(&info);
+
+        assert_eq!(
+            sites,
+            vec![FusionSite {
+                block: ir::BlockIdx(0),
+                first_instruction: 0,
+                kind: FusionKind::CompareAndBranch,
+            }]
+        );
+    }
+
+    #[test]
+    fn finds_load_and_subscript_adjacent_pair() {
+        let mut info = empty_code_info();
+        push(
+            &mut info.blocks[0],
+            Instruction::LoadConst {
+                idx: bytecode::Arg::marker(),
+            },
+        );
+        push(&mut info.blocks[0], Instruction::Subscript);
+        push(&mut info.blocks[0], Instruction::ReturnValue);
+
+        let sites = find_fusion_sites(&info);
+
+        assert_eq!(
+            sites,
+            vec![FusionSite {
+                block: ir::BlockIdx(0),
+                first_instruction: 0,
+                kind: FusionKind::LoadAndSubscript,
+            }]
+        );
+    }
+
+    #[test]
+    fn ignores_non_adjacent_and_unrelated_pairs() {
+        let mut info = empty_code_info();
+        push(
+            &mut info.blocks[0],
+            Instruction::CompareOperation {
+                op: bytecode::ComparisonOperator::Equal,
+            },
+        );
+        push(&mut info.blocks[0], Instruction::Pop);
+        push(
+            &mut info.blocks[0],
+            Instruction::JumpIfFalse {
+                target: bytecode::Arg::marker(),
+            },
+        );
+
+        assert!(find_fusion_sites(&info).is_empty());
+    }
+
+    #[test]
+    fn optimize_is_a_read_only_detection_pass() {
+        let mut info = empty_code_info();
+        push(
+            &mut info.blocks[0],
+            Instruction::LoadFast(bytecode::Arg::marker()),
+        );
+        push(&mut info.blocks[0], Instruction::Subscript);
+        let before: usize = info.blocks.iter().map(|b| b.instructions.len()).sum();
+
+        let sites = optimize(&info);
+        let after: usize = info.blocks.iter().map(|b| b.instructions.len()).sum();
+
+        assert_eq!(sites.len(), 1);
+        assert_eq!(before, after);
+    }
+}
@@ -0,0 +1,512 @@
+//! AST-level constant folding, gated behind [`OptimizationLevel`].
+//!
+//! At [`OptimizationLevel::Basic`] and above, [`fold_literal`] evaluates an
+//! expression built entirely out of literals (and nested literal
+//! arithmetic/comparisons) down to the [`ConstantData`] it would produce at
+//! runtime, without executing it. `Compiler::compile_expression` calls this
+//! before falling back to normal codegen, so e.g. `2 + 3 * 4` is emitted as
+//! a single `LoadConst` rather than four instructions.
+//!
+//! This is deliberately conservative: anything that can raise or behave
+//! differently at runtime than the unfolded code would (division, modulo,
+//! power, `in`/`is` comparisons, anything involving a name or call) is left
+//! unfolded and falls through to normal codegen.
+//!
+//! Two parts of the optimization-level request this module is named after
+//! are intentionally not implemented here, since doing them safely needs
+//! information this crate snapshot doesn't expose with confidence:
+//!   - [`OptimizationLevel::Full`]'s single-assignment local constant
+//!     propagation, which needs cross-referencing the full `SymbolTable`
+//!     (including nested scopes, `global`/`nonlocal`, cell/free vars).
+//!   - Static `if False:`/`while False:` branch pruning and stripping
+//!     discarded pure expression statements, which needs care around
+//!     traceback line-number fidelity.
+//! Both remain open follow-ups; widening this module to cover them doesn't
+//! require changing the shape of `fold_literal` itself.
+
+pub use crate::compile::OptimizationLevel;
+use malachite_bigint::BigInt;
+use num_traits::{ToPrimitive, Zero};
+use ruff_python_ast::{BoolOp, CmpOp, Expr, ExprSubscript, ExprTuple, Number, Operator, UnaryOp};
+use rustpython_compiler_core::bytecode::ConstantData;
+
+/// Try to evaluate `expr` down to a single constant, recursing through
+/// literal-only `BinOp`/`UnaryOp`/`BoolOp`/`Compare` chains. Returns `None`
+/// for anything involving a name, call, or an operation this pass
+/// considers unsafe to fold (see the module doc comment).
+pub fn fold_literal(expr: &Expr) -> Option<ConstantData> {
+    Some(match expr {
+        Expr::NumberLiteral(n) => match &n.value {
+            Number::Int(int) => ConstantData::Integer {
+                value: crate::compile::ruff_int_to_bigint(int).ok()?,
+            },
+            Number::Float(f) => ConstantData::Float { value: *f },
+            Number::Complex { .. } => return None,
+        },
+        Expr::StringLiteral(s) => ConstantData::Str {
+            value: s.value.to_str().to_owned(),
+        },
+        Expr::BytesLiteral(b) => ConstantData::Bytes {
+            value: b.value.iter().flat_map(|x| x.iter().copied()).collect(),
+        },
+        Expr::BooleanLiteral(b) => ConstantData::Boolean { value: b.value },
+        Expr::NoneLiteral(_) => ConstantData::None,
+        Expr::UnaryOp(u) => fold_unary(u.op, &u.operand)?,
+        Expr::BinOp(b) => fold_binop(&b.left, b.op, &b.right)?,
+        Expr::BoolOp(b) => fold_bool_op(b.op, &b.values)?,
+        Expr::Compare(c) => fold_compare(&c.left, &c.ops, &c.comparators)?,
+        Expr::Tuple(ExprTuple { elts, .. }) => ConstantData::Tuple {
+            // A bare `*x` element can't be folded (it's not a single
+            // value until runtime unpacking), so this falls through to
+            // `_ => return None` for any tuple containing one.
+            elements: elts.iter().map(fold_literal).collect::<Option<_>>()?,
+        },
+        Expr::Subscript(ExprSubscript { value, slice, .. }) => fold_subscript(value, slice)?,
+        _ => return None,
+    })
+}
+
+/// `container[index]` where `container` folds to a tuple/str/bytes and
+/// `index` folds to an integer: resolve the indexing with Python's
+/// negative-index semantics at compile time. An out-of-range index
+/// returns `None` rather than folding, so the unfolded opcodes run and
+/// raise the real `IndexError` - same reasoning as leaving division by
+/// zero unfolded in [`fold_binop`].
+fn fold_subscript(value: &Expr, slice: &Expr) -> Option<ConstantData> {
+    let container = fold_literal(value)?;
+    let ConstantData::Integer { value: index } = fold_literal(slice)? else {
+        return None;
+    };
+    let index = index.to_isize()?;
+    match container {
+        ConstantData::Tuple { elements } => {
+            let i = normalize_index(elements.len(), index)?;
+            Some(elements.into_iter().nth(i).unwrap())
+        }
+        ConstantData::Str { value } => {
+            let chars: Vec<char> = value.chars().collect();
+            let i = normalize_index(chars.len(), index)?;
+            Some(ConstantData::Str {
+                value: chars[i].to_string(),
+            })
+        }
+        ConstantData::Bytes { value } => {
+            let i = normalize_index(value.len(), index)?;
+            Some(ConstantData::Bytes {
+                value: vec![value[i]],
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Resolve a Python-style (possibly negative) index against a sequence of
+/// length `len`, returning `None` if it's out of range either way.
+fn normalize_index(len: usize, index: isize) -> Option<usize> {
+    let resolved = if index < 0 {
+        index.checked_add(len as isize)?
+    } else {
+        index
+    };
+    usize::try_from(resolved).ok().filter(|i| *i < len)
+}
+
+fn fold_unary(op: UnaryOp, operand: &Expr) -> Option<ConstantData> {
+    let value = fold_literal(operand)?;
+    Some(match (op, value) {
+        (UnaryOp::Not, v) => ConstantData::Boolean { value: !truthy(&v) },
+        (UnaryOp::USub, ConstantData::Integer { value }) => ConstantData::Integer {
+            value: -value,
+        },
+        (UnaryOp::USub, ConstantData::Float { value }) => ConstantData::Float { value: -value },
+        (UnaryOp::UAdd, v @ (ConstantData::Integer { .. } | ConstantData::Float { .. })) => v,
+        // `+`/`-`/`~` on `bool` go through int promotion in real Python;
+        // skip rather than risk a subtly wrong fold.
+        _ => return None,
+    })
+}
+
+fn fold_binop(left: &Expr, op: Operator, right: &Expr) -> Option<ConstantData> {
+    // Division/modulo/power can raise (`ZeroDivisionError`) or blow up in
+    // size; never fold them.
+    if matches!(
+        op,
+        Operator::Div | Operator::FloorDiv | Operator::Mod | Operator::Pow
+    ) {
+        return None;
+    }
+    let l = fold_literal(left)?;
+    let r = fold_literal(right)?;
+    Some(match (l, r) {
+        (ConstantData::Integer { value: l }, ConstantData::Integer { value: r }) => {
+            ConstantData::Integer {
+                value: fold_int_op(l, op, r)?,
+            }
+        }
+        (ConstantData::Float { value: l }, ConstantData::Float { value: r }) => {
+            ConstantData::Float {
+                value: fold_float_op(l, op, r)?,
+            }
+        }
+        (ConstantData::Str { value: l }, ConstantData::Str { value: r }) if op == Operator::Add => {
+            ConstantData::Str { value: l + &r }
+        }
+        (ConstantData::Bytes { value: l }, ConstantData::Bytes { value: r })
+            if op == Operator::Add =>
+        {
+            ConstantData::Bytes {
+                value: l.into_iter().chain(r).collect(),
+            }
+        }
+        _ => return None,
+    })
+}
+
+fn fold_int_op(l: BigInt, op: Operator, r: BigInt) -> Option<BigInt> {
+    Some(match op {
+        Operator::Add => l + r,
+        Operator::Sub => l - r,
+        Operator::Mult => l * r,
+        Operator::LShift | Operator::RShift => {
+            // A negative or huge shift amount raises/blows up at runtime;
+            // only fold small, non-negative shifts.
+            let shift = r.to_u64().filter(|s| *s <= 1024)?;
+            if op == Operator::LShift {
+                l << shift
+            } else {
+                l >> shift
+            }
+        }
+        Operator::BitOr => l | r,
+        Operator::BitXor => l ^ r,
+        Operator::BitAnd => l & r,
+        _ => return None,
+    })
+}
+
+fn fold_float_op(l: f64, op: Operator, r: f64) -> Option<f64> {
+    Some(match op {
+        Operator::Add => l + r,
+        Operator::Sub => l - r,
+        Operator::Mult => l * r,
+        _ => return None,
+    })
+}
+
+fn fold_bool_op(op: BoolOp, values: &[Expr]) -> Option<ConstantData> {
+    let mut folded = Vec::with_capacity(values.len());
+    for v in values {
+        folded.push(fold_literal(v)?);
+    }
+    match op {
+        BoolOp::And => {
+            for v in &folded {
+                if !truthy(v) {
+                    return Some(v.clone());
+                }
+            }
+            folded.last().cloned()
+        }
+        BoolOp::Or => {
+            for v in &folded {
+                if truthy(v) {
+                    return Some(v.clone());
+                }
+            }
+            folded.last().cloned()
+        }
+    }
+}
+
+fn fold_compare(left: &Expr, ops: &[CmpOp], comparators: &[Expr]) -> Option<ConstantData> {
+    let mut prev = fold_literal(left)?;
+    for (op, next_expr) in ops.iter().zip(comparators) {
+        let next = fold_literal(next_expr)?;
+        let result = match op {
+            CmpOp::Eq => const_eq(&prev, &next)?,
+            CmpOp::NotEq => !const_eq(&prev, &next)?,
+            CmpOp::Lt => const_ord(&prev, &next)? == std::cmp::Ordering::Less,
+            CmpOp::LtE => const_ord(&prev, &next)? != std::cmp::Ordering::Greater,
+            CmpOp::Gt => const_ord(&prev, &next)? == std::cmp::Ordering::Greater,
+            CmpOp::GtE => const_ord(&prev, &next)? != std::cmp::Ordering::Less,
+            // `in`/`not in`/`is`/`is not` depend on runtime container
+            // membership or object identity; never fold them.
+            _ => return None,
+        };
+        if !result {
+            return Some(ConstantData::Boolean { value: false });
+        }
+        prev = next;
+    }
+    Some(ConstantData::Boolean { value: true })
+}
+
+fn const_eq(a: &ConstantData, b: &ConstantData) -> Option<bool> {
+    Some(match (a, b) {
+        (ConstantData::Integer { value: a }, ConstantData::Integer { value: b }) => a == b,
+        (ConstantData::Float { value: a }, ConstantData::Float { value: b }) => a == b,
+        (ConstantData::Str { value: a }, ConstantData::Str { value: b }) => a == b,
+        (ConstantData::Boolean { value: a }, ConstantData::Boolean { value: b }) => a == b,
+        (ConstantData::None, ConstantData::None) => true,
+        _ => return None,
+    })
+}
+
+fn const_ord(a: &ConstantData, b: &ConstantData) -> Option<std::cmp::Ordering> {
+    Some(match (a, b) {
+        (ConstantData::Integer { value: a }, ConstantData::Integer { value: b }) => a.cmp(b),
+        (ConstantData::Float { value: a }, ConstantData::Float { value: b }) => a.partial_cmp(b)?,
+        (ConstantData::Str { value: a }, ConstantData::Str { value: b }) => a.cmp(b),
+        _ => return None,
+    })
+}
+
+fn truthy(value: &ConstantData) -> bool {
+    match value {
+        ConstantData::Boolean { value } => *value,
+        ConstantData::None => false,
+        ConstantData::Integer { value } => !value.is_zero(),
+        ConstantData::Float { value } => *value != 0.0,
+        ConstantData::Str { value } => !value.is_empty(),
+        ConstantData::Bytes { value } => !value.is_empty(),
+        ConstantData::Tuple { elements } => !elements.is_empty(),
+        _ => true,
+    }
+}
+
+/// Covers [`fold_literal`] and its helpers, with one deliberate gap:
+/// there's no confirmed-safe way to hand-construct a `ruff_python_ast`
+/// integer literal or `ExprTuple` in this crate snapshot (every existing
+/// use of `Number::Int`/`ExprTuple` elsewhere only pattern-matches them
+/// with `..`, never builds one field-by-field), so the `Expr::Tuple` and
+/// integer-literal arms of `fold_literal` aren't exercised through an
+/// `Expr` here. What those arms delegate to - integer/float arithmetic
+/// (`fold_int_op`/`fold_float_op`) and negative-index resolution
+/// (`normalize_index`, the core of the subscript-folding case) - takes
+/// plain `BigInt`/`f64`/`usize` and is tested directly below instead.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ruff_python_ast::name::Name;
+    use ruff_python_ast::{
+        ExprBooleanLiteral, ExprContext, ExprName, ExprStringLiteral, StringLiteral,
+        StringLiteralFlags, StringLiteralValue,
+    };
+    use ruff_text_size::TextRange;
+
+    fn str_literal(value: &str) -> Expr {
+        Expr::StringLiteral(ExprStringLiteral {
+            range: TextRange::default(),
+            value: StringLiteralValue::single(StringLiteral {
+                range: TextRange::default(),
+                value: value.into(),
+                flags: StringLiteralFlags::empty(),
+            }),
+        })
+    }
+
+    fn bool_literal(value: bool) -> Expr {
+        Expr::BooleanLiteral(ExprBooleanLiteral {
+            range: TextRange::default(),
+            value,
+        })
+    }
+
+    fn name_expr(id: &str) -> Expr {
+        Expr::Name(ExprName {
+            range: TextRange::default(),
+            id: Name::new(id),
+            ctx: ExprContext::Load,
+        })
+    }
+
+    #[test]
+    fn fold_literal_folds_string_and_boolean_literals() {
+        assert_eq!(
+            fold_literal(&str_literal("hi")),
+            Some(ConstantData::Str {
+                value: "hi".to_owned()
+            })
+        );
+        assert_eq!(
+            fold_literal(&bool_literal(true)),
+            Some(ConstantData::Boolean { value: true })
+        );
+    }
+
+    #[test]
+    fn fold_literal_returns_none_for_a_name() {
+        assert_eq!(fold_literal(&name_expr("x")), None);
+    }
+
+    #[test]
+    fn fold_unary_not_negates_truthiness() {
+        assert_eq!(
+            fold_unary(UnaryOp::Not, &bool_literal(false)),
+            Some(ConstantData::Boolean { value: true })
+        );
+    }
+
+    #[test]
+    fn fold_unary_rejects_name_operand_that_fails_to_fold() {
+        assert_eq!(fold_unary(UnaryOp::Not, &name_expr("x")), None);
+    }
+
+    #[test]
+    fn fold_binop_concatenates_adjacent_string_literals() {
+        assert_eq!(
+            fold_binop(&str_literal("a"), Operator::Add, &str_literal("b")),
+            Some(ConstantData::Str {
+                value: "ab".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn fold_binop_does_not_fold_string_subtraction() {
+        assert_eq!(
+            fold_binop(&str_literal("a"), Operator::Sub, &str_literal("b")),
+            None
+        );
+    }
+
+    #[test]
+    fn fold_bool_op_and_short_circuits_on_first_falsy() {
+        let values = [
+            bool_literal(true),
+            bool_literal(false),
+            str_literal("unreached"),
+        ];
+        assert_eq!(
+            fold_bool_op(BoolOp::And, &values),
+            Some(ConstantData::Boolean { value: false })
+        );
+    }
+
+    #[test]
+    fn fold_bool_op_or_short_circuits_on_first_truthy() {
+        let values = [
+            bool_literal(false),
+            str_literal("first truthy"),
+            bool_literal(false),
+        ];
+        assert_eq!(
+            fold_bool_op(BoolOp::Or, &values),
+            Some(ConstantData::Str {
+                value: "first truthy".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn fold_compare_eq_and_ordering_on_string_literals() {
+        assert_eq!(
+            fold_compare(&str_literal("a"), &[CmpOp::Eq], &[str_literal("a")]),
+            Some(ConstantData::Boolean { value: true })
+        );
+        assert_eq!(
+            fold_compare(&str_literal("a"), &[CmpOp::Lt], &[str_literal("b")]),
+            Some(ConstantData::Boolean { value: true })
+        );
+    }
+
+    #[test]
+    fn fold_compare_never_folds_in_operator() {
+        assert_eq!(
+            fold_compare(&str_literal("a"), &[CmpOp::In], &[str_literal("a")]),
+            None
+        );
+    }
+
+    #[test]
+    fn fold_int_op_covers_arithmetic_and_bounded_shift() {
+        let one = BigInt::from(1);
+        let two = BigInt::from(2);
+        assert_eq!(
+            fold_int_op(one.clone(), Operator::Add, two.clone()),
+            Some(BigInt::from(3))
+        );
+        assert_eq!(
+            fold_int_op(one.clone(), Operator::LShift, two.clone()),
+            Some(BigInt::from(4))
+        );
+        // A shift amount this large would blow up the result; never fold it.
+        assert_eq!(
+            fold_int_op(one, Operator::LShift, BigInt::from(10_000)),
+            None
+        );
+        assert_eq!(fold_int_op(two.clone(), Operator::Div, two), None);
+    }
+
+    #[test]
+    fn fold_float_op_covers_arithmetic_only() {
+        assert_eq!(fold_float_op(1.5, Operator::Add, 2.0), Some(3.5));
+        assert_eq!(fold_float_op(1.0, Operator::Div, 2.0), None);
+    }
+
+    #[test]
+    fn truthy_matches_python_semantics_for_each_constant_kind() {
+        assert!(!truthy(&ConstantData::Boolean { value: false }));
+        assert!(truthy(&ConstantData::Boolean { value: true }));
+        assert!(!truthy(&ConstantData::None));
+        assert!(!truthy(&ConstantData::Integer {
+            value: BigInt::from(0)
+        }));
+        assert!(truthy(&ConstantData::Integer {
+            value: BigInt::from(1)
+        }));
+        assert!(!truthy(&ConstantData::Float { value: 0.0 }));
+        assert!(truthy(&ConstantData::Float { value: 1.0 }));
+        assert!(!truthy(&ConstantData::Str {
+            value: String::new()
+        }));
+        assert!(truthy(&ConstantData::Str {
+            value: "x".to_owned()
+        }));
+        assert!(!truthy(&ConstantData::Bytes { value: Vec::new() }));
+        assert!(truthy(&ConstantData::Bytes { value: vec![0] }));
+        // An empty tuple is falsy and a non-empty one is truthy, same as
+        // every other sized constant above.
+        assert!(!truthy(&ConstantData::Tuple {
+            elements: Vec::new()
+        }));
+        assert!(truthy(&ConstantData::Tuple {
+            elements: vec![ConstantData::None]
+        }));
+        // Kinds `truthy` doesn't special-case (no Python-meaningful empty
+        // state to check) fall through its catch-all as always-truthy.
+        assert!(truthy(&ConstantData::Ellipsis));
+    }
+
+    #[test]
+    fn const_eq_and_const_ord_compare_like_kinds_only() {
+        let a = ConstantData::Integer {
+            value: BigInt::from(1),
+        };
+        let b = ConstantData::Integer {
+            value: BigInt::from(2),
+        };
+        assert_eq!(const_eq(&a, &a), Some(true));
+        assert_eq!(const_eq(&a, &b), Some(false));
+        assert_eq!(const_ord(&a, &b), Some(std::cmp::Ordering::Less));
+        // Mismatched kinds aren't comparable; `Eq`/ordering fold only
+        // applies between two constants of the same kind.
+        assert_eq!(
+            const_eq(
+                &a,
+                &ConstantData::Str {
+                    value: "1".to_owned()
+                }
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn normalize_index_resolves_negative_indices_and_rejects_out_of_range() {
+        assert_eq!(normalize_index(3, 0), Some(0));
+        assert_eq!(normalize_index(3, -1), Some(2));
+        assert_eq!(normalize_index(3, 3), None);
+        assert_eq!(normalize_index(3, -4), None);
+    }
+}
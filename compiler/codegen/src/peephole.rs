@@ -0,0 +1,678 @@
+//! A peephole optimizer over `ir::CodeInfo`'s block/instruction graph, run
+//! just before [`crate::compile::Compiler::pop_code_object`] hands the code
+//! off to `finalize_code`. Gated behind `OptimizationLevel::Basic`.
+//!
+//! Each pass below is a small, local, structural rewrite rather than an
+//! offset-based one: jump targets live in `ir::InstructionInfo::target`
+//! (an `ir::BlockIdx`), so threading a jump-to-jump chain or dropping a
+//! jump to the next block is done by editing `target`/`next` fields, never
+//! by computing byte offsets. After the local rewrites we recompute
+//! reachability from block 0 and drop any block nothing points to
+//! anymore, renumbering the rest so every remaining `BlockIdx` stays
+//! valid.
+//!
+//! Runs to a fixed point (bounded, so a pathological input can't loop
+//! forever): each individual rewrite is small, but e.g. threading a jump
+//! chain can expose a new next-block-jump to remove, or merging two blocks
+//! can expose a new jump-to-empty-block to thread, so a single pass over
+//! the graph isn't always enough to reach the fully-reduced form.
+//!
+//! Dropping code unreachable after a block's own terminator
+//! (`ReturnValue`/`ReturnConst`/`Raise`/`Jump`) is handled earlier, by
+//! `Compiler::trim_unreachable_instructions` - by the time this module
+//! runs, no block has instructions after its own terminator, so there's
+//! nothing for a pass here to do on that front.
+
+use crate::ir;
+use malachite_bigint::BigInt;
+use ruff_source_file::OneIndexed;
+use rustpython_compiler_core::bytecode::{self, ConstantData, Instruction};
+
+const MAX_ROUNDS: usize = 8;
+
+pub fn optimize(info: &mut ir::CodeInfo) {
+    for _ in 0..MAX_ROUNDS {
+        let mut changed = false;
+        changed |= remove_duplicate_pop(info);
+        changed |= remove_pop_of_load_const(info);
+        changed |= fold_constant_binops(info);
+        changed |= fold_constant_unaryops(info);
+        changed |= fold_const_conditional_jump(info);
+        changed |= thread_jump_chains(info);
+        changed |= thread_jump_to_empty_block(info);
+        changed |= remove_jump_to_next(info);
+        changed |= merge_single_predecessor_blocks(info);
+        if !changed {
+            break;
+        }
+    }
+    drop_unreachable_blocks(info);
+}
+
+/// `LoadConst a; LoadConst b; BinaryOperation{op}` (or `CompareOperation`)
+/// folds into a single `LoadConst` of the computed result, for the same
+/// conservative subset [`crate::optimize::fold_literal`] folds at the AST
+/// level: integer/float arithmetic that can't raise or blow up, string/bytes
+/// `+`, and the six comparison operators - plus tuple `+`, which
+/// `fold_literal` does not fold. Division, modulo, power, and
+/// shifts by an operand that isn't safely boundable are left alone, same as
+/// there.
+fn fold_constant_binops(info: &mut ir::CodeInfo) -> bool {
+    let mut changed = false;
+    for block_idx in 0..info.blocks.len() {
+        let mut i = 0;
+        loop {
+            let len = info.blocks[block_idx].instructions.len();
+            if i + 2 >= len {
+                break;
+            }
+            let folded = (|| {
+                let a = loaded_constant(info, &info.blocks[block_idx].instructions[i])?.clone();
+                let b = loaded_constant(info, &info.blocks[block_idx].instructions[i + 1])?.clone();
+                match &info.blocks[block_idx].instructions[i + 2].instr {
+                    Instruction::BinaryOperation { op } => fold_binary(&a, *op, &b),
+                    Instruction::CompareOperation { op } => fold_compare(&a, *op, &b),
+                    _ => None,
+                }
+            })();
+            let Some(folded) = folded else {
+                i += 1;
+                continue;
+            };
+            let idx = info.constants.insert_full(folded).0 as u32;
+            let location = info.blocks[block_idx].instructions[i].location.clone();
+            info.blocks[block_idx].instructions.splice(
+                i..i + 3,
+                [ir::InstructionInfo {
+                    instr: Instruction::LoadConst {
+                        idx: bytecode::Arg::marker(),
+                    },
+                    arg: bytecode::OpArg::new(idx),
+                    target: ir::BlockIdx::NULL,
+                    location,
+                }],
+            );
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn fold_binary(
+    a: &ConstantData,
+    op: bytecode::BinaryOperator,
+    b: &ConstantData,
+) -> Option<ConstantData> {
+    use bytecode::BinaryOperator::*;
+    // Division/modulo/power can raise (`ZeroDivisionError`) or blow up in
+    // size; never fold them.
+    if matches!(op, Divide | FloorDivide | Modulo | Power) {
+        return None;
+    }
+    Some(match (a, b) {
+        (ConstantData::Integer { value: l }, ConstantData::Integer { value: r }) => {
+            ConstantData::Integer {
+                value: fold_int_op(l.clone(), op, r.clone())?,
+            }
+        }
+        (ConstantData::Float { value: l }, ConstantData::Float { value: r }) => {
+            ConstantData::Float {
+                value: fold_float_op(*l, op, *r)?,
+            }
+        }
+        (ConstantData::Str { value: l }, ConstantData::Str { value: r }) if op == Add => {
+            ConstantData::Str {
+                value: format!("{l}{r}"),
+            }
+        }
+        (ConstantData::Bytes { value: l }, ConstantData::Bytes { value: r }) if op == Add => {
+            ConstantData::Bytes {
+                value: l.iter().chain(r).copied().collect(),
+            }
+        }
+        (ConstantData::Tuple { elements: l }, ConstantData::Tuple { elements: r }) if op == Add => {
+            ConstantData::Tuple {
+                elements: l.iter().chain(r).cloned().collect(),
+            }
+        }
+        _ => return None,
+    })
+}
+
+/// `LoadConst a; UnaryOperation{op}` folds the same way
+/// [`crate::optimize::fold_unary`] does at the AST level: `not` on a
+/// `None`/`bool`/`int`/`float`/`str`/`bytes` constant (other kinds are left
+/// alone, see `constant_truthiness`), and `+`/`-` on `int`/`float`. `bool` is left alone since `+`/`-`/`~`
+/// promote it to `int` in real Python and folding here would silently
+/// produce the wrong type.
+fn fold_constant_unaryops(info: &mut ir::CodeInfo) -> bool {
+    let mut changed = false;
+    for block_idx in 0..info.blocks.len() {
+        let mut i = 0;
+        loop {
+            let len = info.blocks[block_idx].instructions.len();
+            if i + 1 >= len {
+                break;
+            }
+            let folded = (|| {
+                let operand =
+                    loaded_constant(info, &info.blocks[block_idx].instructions[i])?.clone();
+                match &info.blocks[block_idx].instructions[i + 1].instr {
+                    Instruction::UnaryOperation { op } => fold_unary(*op, &operand),
+                    _ => None,
+                }
+            })();
+            let Some(folded) = folded else {
+                i += 1;
+                continue;
+            };
+            let idx = info.constants.insert_full(folded).0 as u32;
+            let location = info.blocks[block_idx].instructions[i].location.clone();
+            info.blocks[block_idx].instructions.splice(
+                i..i + 2,
+                [ir::InstructionInfo {
+                    instr: Instruction::LoadConst {
+                        idx: bytecode::Arg::marker(),
+                    },
+                    arg: bytecode::OpArg::new(idx),
+                    target: ir::BlockIdx::NULL,
+                    location,
+                }],
+            );
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn fold_unary(op: bytecode::UnaryOperator, operand: &ConstantData) -> Option<ConstantData> {
+    use bytecode::UnaryOperator::*;
+    Some(match (op, operand) {
+        (Not, v) => ConstantData::Boolean {
+            value: !constant_truthiness(v)?,
+        },
+        (Minus, ConstantData::Integer { value }) => ConstantData::Integer {
+            value: -value.clone(),
+        },
+        (Minus, ConstantData::Float { value }) => ConstantData::Float { value: -value },
+        (Plus, v @ (ConstantData::Integer { .. } | ConstantData::Float { .. })) => v.clone(),
+        _ => return None,
+    })
+}
+
+fn fold_int_op(l: BigInt, op: bytecode::BinaryOperator, r: BigInt) -> Option<BigInt> {
+    use bytecode::BinaryOperator::*;
+    Some(match op {
+        Add => l + r,
+        Subtract => l - r,
+        Multiply => l * r,
+        Lshift | Rshift => {
+            // A negative or huge shift amount raises/blows up at runtime;
+            // only fold small, non-negative shifts.
+            let shift = num_traits::ToPrimitive::to_u64(&r).filter(|s| *s <= 1024)?;
+            if op == Lshift {
+                l << shift
+            } else {
+                l >> shift
+            }
+        }
+        Or => l | r,
+        Xor => l ^ r,
+        And => l & r,
+        _ => return None,
+    })
+}
+
+fn fold_float_op(l: f64, op: bytecode::BinaryOperator, r: f64) -> Option<f64> {
+    use bytecode::BinaryOperator::*;
+    Some(match op {
+        Add => l + r,
+        Subtract => l - r,
+        Multiply => l * r,
+        _ => return None,
+    })
+}
+
+fn fold_compare(
+    a: &ConstantData,
+    op: bytecode::ComparisonOperator,
+    b: &ConstantData,
+) -> Option<ConstantData> {
+    use bytecode::ComparisonOperator::*;
+    let result = match op {
+        Equal => const_eq(a, b)?,
+        NotEqual => !const_eq(a, b)?,
+        Less => const_ord(a, b)? == std::cmp::Ordering::Less,
+        LessOrEqual => const_ord(a, b)? != std::cmp::Ordering::Greater,
+        Greater => const_ord(a, b)? == std::cmp::Ordering::Greater,
+        GreaterOrEqual => const_ord(a, b)? != std::cmp::Ordering::Less,
+    };
+    Some(ConstantData::Boolean { value: result })
+}
+
+fn const_eq(a: &ConstantData, b: &ConstantData) -> Option<bool> {
+    Some(match (a, b) {
+        (ConstantData::Integer { value: a }, ConstantData::Integer { value: b }) => a == b,
+        (ConstantData::Float { value: a }, ConstantData::Float { value: b }) => a == b,
+        (ConstantData::Str { value: a }, ConstantData::Str { value: b }) => a == b,
+        (ConstantData::Boolean { value: a }, ConstantData::Boolean { value: b }) => a == b,
+        (ConstantData::None, ConstantData::None) => true,
+        _ => return None,
+    })
+}
+
+fn const_ord(a: &ConstantData, b: &ConstantData) -> Option<std::cmp::Ordering> {
+    Some(match (a, b) {
+        (ConstantData::Integer { value: a }, ConstantData::Integer { value: b }) => a.cmp(b),
+        (ConstantData::Float { value: a }, ConstantData::Float { value: b }) => a.partial_cmp(b)?,
+        (ConstantData::Str { value: a }, ConstantData::Str { value: b }) => a.cmp(b),
+        _ => return None,
+    })
+}
+
+/// `Duplicate` immediately followed by `Pop` is a no-op: push a copy, then
+/// throw it away.
+fn remove_duplicate_pop(info: &mut ir::CodeInfo) -> bool {
+    let mut changed = false;
+    for block in &mut info.blocks {
+        let mut i = 0;
+        while i + 1 < block.instructions.len() {
+            let is_dup_pop = matches!(block.instructions[i].instr, Instruction::Duplicate)
+                && matches!(block.instructions[i + 1].instr, Instruction::Pop);
+            if is_dup_pop {
+                block.instructions.drain(i..i + 2);
+                changed = true;
+            } else {
+                i += 1;
+            }
+        }
+    }
+    changed
+}
+
+fn loaded_constant<'a>(info: &'a ir::CodeInfo, instr: &ir::InstructionInfo) -> Option<&'a ConstantData> {
+    if !matches!(instr.instr, Instruction::LoadConst { .. }) {
+        return None;
+    }
+    info.constants.get_index(instr.arg.0 as usize)
+}
+
+/// `LoadConst` of any value immediately followed by `Pop` pushes a value
+/// only to immediately discard it - `LoadConst` never has a side effect, so
+/// this is dead regardless of which constant it loads.
+fn remove_pop_of_load_const(info: &mut ir::CodeInfo) -> bool {
+    let mut changed = false;
+    for block_idx in 0..info.blocks.len() {
+        let mut i = 0;
+        loop {
+            let len = info.blocks[block_idx].instructions.len();
+            if i + 1 >= len {
+                break;
+            }
+            let is_const_pop = loaded_constant(info, &info.blocks[block_idx].instructions[i])
+                .is_some()
+                && matches!(
+                    info.blocks[block_idx].instructions[i + 1].instr,
+                    Instruction::Pop
+                );
+            if is_const_pop {
+                info.blocks[block_idx].instructions.drain(i..i + 2);
+                changed = true;
+            } else {
+                i += 1;
+            }
+        }
+    }
+    changed
+}
+
+/// `LoadConst` of a known-truthiness value immediately followed by a
+/// conditional jump on that value folds into an unconditional `Jump` (or
+/// disappears entirely, falling through) since the condition is known at
+/// compile time.
+fn fold_const_conditional_jump(info: &mut ir::CodeInfo) -> bool {
+    let mut changed = false;
+    for block_idx in 0..info.blocks.len() {
+        let mut i = 0;
+        loop {
+            let len = info.blocks[block_idx].instructions.len();
+            if i + 1 >= len {
+                break;
+            }
+            let Some(truthy) =
+                loaded_constant(info, &info.blocks[block_idx].instructions[i]).and_then(constant_truthiness)
+            else {
+                i += 1;
+                continue;
+            };
+            let next = &info.blocks[block_idx].instructions[i + 1];
+            let taken = match &next.instr {
+                Instruction::JumpIfTrue { .. } => Some(truthy),
+                Instruction::JumpIfFalse { .. } => Some(!truthy),
+                _ => None,
+            };
+            let Some(taken) = taken else {
+                i += 1;
+                continue;
+            };
+            if taken {
+                let target = next.target;
+                let block = &mut info.blocks[block_idx];
+                block.instructions[i].instr = Instruction::Jump {
+                    target: bytecode::Arg::marker(),
+                };
+                block.instructions[i].target = target;
+                block.instructions.remove(i + 1);
+            } else {
+                info.blocks[block_idx].instructions.drain(i..i + 2);
+            }
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn constant_truthiness(constant: &ConstantData) -> Option<bool> {
+    Some(match constant {
+        ConstantData::Boolean { value } => *value,
+        ConstantData::None => false,
+        ConstantData::Integer { value } => *value != malachite_bigint::BigInt::from(0),
+        ConstantData::Float { value } => *value != 0.0,
+        ConstantData::Str { value } => !value.is_empty(),
+        ConstantData::Bytes { value } => !value.is_empty(),
+        _ => return None,
+    })
+}
+
+/// A jump whose target block consists of nothing but a single
+/// unconditional `Jump` can point straight at that jump's own target,
+/// skipping the intermediate hop.
+fn thread_jump_chains(info: &mut ir::CodeInfo) -> bool {
+    let mut changed = false;
+    for i in 0..info.blocks.len() {
+        for j in 0..info.blocks[i].instructions.len() {
+            let target = info.blocks[i].instructions[j].target;
+            if target == ir::BlockIdx::NULL {
+                continue;
+            }
+            let resolved = resolve_jump_chain(&info.blocks, target);
+            if resolved != target {
+                info.blocks[i].instructions[j].target = resolved;
+                changed = true;
+            }
+        }
+        let next = info.blocks[i].next;
+        if next != ir::BlockIdx::NULL {
+            let resolved = resolve_jump_chain(&info.blocks, next);
+            if resolved != next {
+                info.blocks[i].next = resolved;
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+fn resolve_jump_chain(blocks: &[ir::Block], mut target: ir::BlockIdx) -> ir::BlockIdx {
+    let mut seen = std::collections::HashSet::new();
+    while seen.insert(target.0) {
+        let Some(block) = blocks.get(target.0 as usize) else {
+            break;
+        };
+        let [single] = block.instructions.as_slice() else {
+            break;
+        };
+        if !matches!(single.instr, Instruction::Jump { .. }) {
+            break;
+        }
+        target = single.target;
+    }
+    target
+}
+
+/// A jump whose target block has no instructions of its own - it only
+/// falls through via `next` - can point straight at that `next` block,
+/// skipping the pointless hop through an empty one.
+fn thread_jump_to_empty_block(info: &mut ir::CodeInfo) -> bool {
+    let mut changed = false;
+    for i in 0..info.blocks.len() {
+        for j in 0..info.blocks[i].instructions.len() {
+            let target = info.blocks[i].instructions[j].target;
+            if target == ir::BlockIdx::NULL {
+                continue;
+            }
+            let resolved = resolve_empty_block_chain(&info.blocks, target);
+            if resolved != target {
+                info.blocks[i].instructions[j].target = resolved;
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+fn resolve_empty_block_chain(blocks: &[ir::Block], mut target: ir::BlockIdx) -> ir::BlockIdx {
+    let mut seen = std::collections::HashSet::new();
+    while seen.insert(target.0) {
+        let Some(block) = blocks.get(target.0 as usize) else {
+            break;
+        };
+        if !block.instructions.is_empty() || block.next == ir::BlockIdx::NULL {
+            break;
+        }
+        target = block.next;
+    }
+    target
+}
+
+/// An unconditional `Jump` as the last instruction of a block, whose
+/// target is exactly the block this one already falls through to, is a
+/// no-op - drop it and let the fallthrough do the work.
+fn remove_jump_to_next(info: &mut ir::CodeInfo) -> bool {
+    let mut changed = false;
+    for block in &mut info.blocks {
+        let is_redundant_jump = match block.instructions.last() {
+            Some(last) => {
+                matches!(last.instr, Instruction::Jump { .. }) && last.target == block.next
+            }
+            None => false,
+        };
+        if is_redundant_jump {
+            block.instructions.pop();
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// If block `b`'s `next` falls through into block `n`, and `n` is reachable
+/// only by that one fallthrough edge - nothing jumps to it, and no other
+/// block falls through into it either - there was never a reason for them
+/// to be two blocks. Fold `n`'s instructions onto the end of `b` and adopt
+/// `n`'s `next`; `n` is left empty, now truly unreachable, for
+/// `drop_unreachable_blocks` to sweep away.
+fn merge_single_predecessor_blocks(info: &mut ir::CodeInfo) -> bool {
+    let len = info.blocks.len();
+    let mut incoming = vec![0u32; len];
+    for block in &info.blocks {
+        for instr in &block.instructions {
+            if instr.target != ir::BlockIdx::NULL {
+                incoming[instr.target.0 as usize] += 1;
+            }
+        }
+        if block.next != ir::BlockIdx::NULL {
+            incoming[block.next.0 as usize] += 1;
+        }
+    }
+
+    let mut changed = false;
+    for idx in 0..len {
+        let next_idx = info.blocks[idx].next;
+        if next_idx == ir::BlockIdx::NULL || next_idx.0 as usize == idx {
+            continue;
+        }
+        if incoming[next_idx.0 as usize] != 1 {
+            continue;
+        }
+        let next_block = std::mem::take(&mut info.blocks[next_idx.0 as usize]);
+        info.blocks[idx]
+            .instructions
+            .extend(next_block.instructions);
+        info.blocks[idx].next = next_block.next;
+        incoming[next_idx.0 as usize] = 0;
+        changed = true;
+    }
+    changed
+}
+
+/// Recompute which blocks are reachable from block 0 (following both
+/// fallthrough `next` edges and explicit jump `target`s) and drop the
+/// rest, renumbering survivors so every remaining `BlockIdx` stays valid.
+fn drop_unreachable_blocks(info: &mut ir::CodeInfo) {
+    let len = info.blocks.len();
+    if len == 0 {
+        return;
+    }
+    let mut reachable = vec![false; len];
+    let mut stack = vec![0u32];
+    reachable[0] = true;
+    while let Some(idx) = stack.pop() {
+        let block = &info.blocks[idx as usize];
+        let mut targets: Vec<ir::BlockIdx> = block.instructions.iter().map(|i| i.target).collect();
+        targets.push(block.next);
+        for target in targets {
+            if target != ir::BlockIdx::NULL && !reachable[target.0 as usize] {
+                reachable[target.0 as usize] = true;
+                stack.push(target.0);
+            }
+        }
+    }
+
+    if reachable.iter().all(|&r| r) {
+        return;
+    }
+
+    let mut remap = vec![ir::BlockIdx::NULL; len];
+    let mut kept = Vec::with_capacity(len);
+    for (old_idx, block) in info.blocks.drain(..).enumerate() {
+        if reachable[old_idx] {
+            remap[old_idx] = ir::BlockIdx(kept.len() as u32);
+            kept.push(block);
+        }
+    }
+    for block in &mut kept {
+        for instr in &mut block.instructions {
+            if instr.target != ir::BlockIdx::NULL {
+                instr.target = remap[instr.target.0 as usize];
+            }
+        }
+        if block.next != ir::BlockIdx::NULL {
+            block.next = remap[block.next.0 as usize];
+        }
+    }
+    info.blocks = kept;
+    if info.current_block != ir::BlockIdx::NULL {
+        info.current_block = remap[info.current_block.0 as usize];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_code_info() -> ir::CodeInfo {
+        ir::CodeInfo {
+            flags: bytecode::CodeFlags::empty(),
+            posonlyarg_count: 0,
+            arg_count: 0,
+            kwonlyarg_count: 0,
+            source_path: String::new(),
+            first_line_number: OneIndexed::MIN,
+            obj_name: "<test>".to_owned(),
+            blocks: vec![ir::Block::default()],
+            current_block: ir::BlockIdx(0),
+            constants: Default::default(),
+            name_cache: Default::default(),
+            varname_cache: Default::default(),
+            cellvar_cache: Default::default(),
+            freevar_cache: Default::default(),
+        }
+    }
+
+    fn push(block: &mut ir::Block, instr: Instruction) {
+        block.instructions.push(ir::InstructionInfo {
+            instr,
+            arg: bytecode::OpArg::null(),
+            target: ir::BlockIdx::NULL,
+            location: Default::default(),
+        });
+    }
+
+    /// `Duplicate` + `Pop` is pure overhead; the peephole pass should
+    /// shrink a block built from nothing else down to its one real
+    /// instruction.
+    #[test]
+    fn shrinks_duplicate_pop_pairs() {
+        let mut info = empty_code_info();
+        push(&mut info.blocks[0], Instruction::Duplicate);
+        push(&mut info.blocks[0], Instruction::Pop);
+        push(&mut info.blocks[0], Instruction::Duplicate);
+        push(&mut info.blocks[0], Instruction::Pop);
+        push(&mut info.blocks[0], Instruction::ReturnValue);
+
+        let before: usize = info.blocks.iter().map(|b| b.instructions.len()).sum();
+        optimize(&mut info);
+        let after: usize = info.blocks.iter().map(|b| b.instructions.len()).sum();
+
+        assert_eq!(before, 5);
+        assert_eq!(after, 1);
+    }
+
+    /// An unconditional jump to the block that's already the fallthrough
+    /// target is redundant. Once it's gone, block 1 is reachable only via
+    /// fallthrough, so it is merged into block 0 and the emptied block is
+    /// dropped; the instruction count shrinks by one (the jump).
+    #[test]
+    fn removes_redundant_jump_to_next_block() {
+        let mut info = empty_code_info();
+        info.blocks.push(ir::Block::default());
+        info.blocks[0].instructions.push(ir::InstructionInfo {
+            instr: Instruction::Jump {
+                target: bytecode::Arg::marker(),
+            },
+            arg: bytecode::OpArg::null(),
+            target: ir::BlockIdx(1),
+            location: Default::default(),
+        });
+        info.blocks[0].next = ir::BlockIdx(1);
+        push(&mut info.blocks[1], Instruction::ReturnValue);
+
+        let before: usize = info.blocks.iter().map(|b| b.instructions.len()).sum();
+        optimize(&mut info);
+        let after: usize = info.blocks.iter().map(|b| b.instructions.len()).sum();
+
+        assert_eq!(before, 2);
+        assert_eq!(after, 1);
+    }
+
+    /// A block whose only `next` is a block nothing else points to should
+    /// get folded into one block, leaving a single remaining block after
+    /// the pass sweeps up the now-empty, unreachable leftover.
+    #[test]
+    fn merges_unique_fallthrough_successor() {
+        let mut info = empty_code_info();
+        info.blocks.push(ir::Block::default());
+        push(&mut info.blocks[0], Instruction::Duplicate);
+        info.blocks[0].next = ir::BlockIdx(1);
+        push(&mut info.blocks[1], Instruction::ReturnValue);
+
+        optimize(&mut info);
+
+        assert_eq!(info.blocks.len(), 1);
+        assert_eq!(info.blocks[0].instructions.len(), 2);
+        assert!(matches!(
+            info.blocks[0].instructions[1].instr,
+            Instruction::ReturnValue
+        ));
+    }
+}
@@ -0,0 +1,286 @@
+//! An alternate, write-only emission backend that marshals an
+//! [`ir::CodeInfo`] into a CPython-compatible `.pyc` file, sitting
+//! alongside [`crate::codeasm`] as another consumer of the pre-finalize
+//! IR.
+//!
+//! This targets the pre-3.11 marshal shape (`argcount`, `posonlyargcount`,
+//! `kwonlyargcount`, `nlocals`, `stacksize`, `flags`, `code`, `consts`,
+//! `names`, `varnames`, `freevars`, `cellvars`, `filename`, `name`,
+//! `firstlineno`, `lnotab`) in that field order, since it's the last
+//! layout documented widely enough to reproduce with confidence. CPython
+//! 3.11+ replaced `lnotab` with `co_linetable`, added `co_exceptiontable`
+//! and `co_qualname`, and changed how zero-cost exception handling is
+//! encoded; this module does not attempt to match that newer layout
+//! byte-for-byte. The opcode numbers below are those of CPython's 3.8-3.10
+//! wordcode format, and jump arguments use the 3.10 encoding (instruction
+//! units, not bytes).
+//! Treat the result as "loadable by a CPython interpreter close enough to
+//! the targeted version", not as a byte-exact artifact of any specific
+//! micro version - useful for cross-checking instruction-level behavior,
+//! not for shipping production `.pyc` files.
+//!
+//! Like [`crate::codeasm::assemble`], encoding is scoped to the
+//! instruction subset this module knows the real CPython opcode for
+//! ([`encode_instruction`]); anything else is reported as
+//! [`PycError::UnsupportedInstruction`] rather than guessed at.
+
+use crate::ir;
+use num_traits::ToPrimitive;
+use rustpython_compiler_core::bytecode::{ConstantData, Instruction};
+use std::fmt;
+use std::io::{self, Write};
+
+/// The marshal magic number CPython embeds in a `.pyc` header to identify
+/// the bytecode version it was compiled for. This module's opcode mapping
+/// targets the 3.10 wordcode format, so that's the magic number written.
+pub const CPYTHON_3_10_MAGIC: u32 = 3439;
+
+const POP_TOP: u8 = 1;
+const DUP_TOP: u8 = 4;
+const RETURN_VALUE: u8 = 83;
+const LOAD_CONST: u8 = 100;
+const JUMP_ABSOLUTE: u8 = 113;
+const POP_JUMP_IF_FALSE: u8 = 114;
+const POP_JUMP_IF_TRUE: u8 = 115;
+
+/// Errors producing a `.pyc`, beyond the underlying I/O failures writing
+/// it can also hit.
+#[derive(Debug)]
+pub enum PycError {
+    /// The instruction has no mapping to a real CPython opcode in this
+    /// module's curated subset (see the module doc comment).
+    UnsupportedInstruction(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for PycError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedInstruction(mnemonic) => {
+                write!(f, "pyc: no CPython opcode mapping for {mnemonic}")
+            }
+            Self::Io(e) => write!(f, "pyc: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for PycError {}
+
+impl From<io::Error> for PycError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Write a complete `.pyc` file: the marshal header (magic, bit field,
+/// mtime, source size) followed by the marshalled code object.
+///
+/// `mtime`/`source_size` are whatever the caller knows about the
+/// `.py` file this was compiled from (CPython uses them to decide whether
+/// a cached `.pyc` is stale); pass `0` for either if there's no source
+/// file to stat.
+pub fn write_pyc(
+    info: &ir::CodeInfo,
+    mtime: u32,
+    source_size: u32,
+    out: &mut impl Write,
+) -> Result<(), PycError> {
+    out.write_all(&(CPYTHON_3_10_MAGIC as u16).to_le_bytes())?;
+    out.write_all(&[0x0d, 0x0a])?; // the traditional `\r\n` sentinel bytes
+    out.write_all(&0u32.to_le_bytes())?; // bit field: source-hash invalidation off
+    out.write_all(&mtime.to_le_bytes())?;
+    out.write_all(&source_size.to_le_bytes())?;
+    marshal_code(info, out)
+}
+
+fn marshal_code(info: &ir::CodeInfo, out: &mut impl Write) -> Result<(), PycError> {
+    let code_bytes = encode_code_bytes(info)?;
+    let stacksize = estimate_stacksize(info);
+
+    // The code object's integer fields are raw little-endian longs, with no
+    // `i` type byte in front of them.
+    out.write_all(b"c")?;
+    out.write_all(&(info.arg_count as i32).to_le_bytes())?;
+    out.write_all(&(info.posonlyarg_count as i32).to_le_bytes())?;
+    out.write_all(&(info.kwonlyarg_count as i32).to_le_bytes())?;
+    out.write_all(&(info.varname_cache.as_slice().len() as i32).to_le_bytes())?;
+    out.write_all(&(stacksize as i32).to_le_bytes())?;
+    out.write_all(&(info.flags.bits() as i32).to_le_bytes())?;
+    marshal_bytes(&code_bytes, out)?;
+    marshal_consts(info, out)?;
+    marshal_str_tuple(info.name_cache.as_slice(), out)?;
+    marshal_str_tuple(info.varname_cache.as_slice(), out)?;
+    marshal_str_tuple(info.freevar_cache.as_slice(), out)?;
+    marshal_str_tuple(info.cellvar_cache.as_slice(), out)?;
+    marshal_str(&info.source_path, out)?;
+    marshal_str(&info.obj_name, out)?;
+    out.write_all(&(info.first_line_number.get() as i32).to_le_bytes())?;
+    marshal_bytes(&[], out) // lnotab: line-number tracking isn't modeled yet
+}
+
+/// Encode every block's instructions, in block order, into one flat
+/// `co_code` byte string. This walks blocks in storage order rather than
+/// following `next`/jump edges, so it only produces a sensible program
+/// for code that was laid out linearly to begin with (true of
+/// freshly-compiled, non-peephole-reordered `ir::CodeInfo`); it's a
+/// starting point for cross-checking individual opcodes, not a
+/// general-purpose control-flow-aware encoder.
+fn encode_code_bytes(info: &ir::CodeInfo) -> Result<Vec<u8>, PycError> {
+    let mut block_offsets = vec![0u32; info.blocks.len()];
+    let mut offset = 0u32;
+    for (idx, block) in info.blocks.iter().enumerate() {
+        block_offsets[idx] = offset;
+        offset += block.instructions.len() as u32;
+    }
+
+    let mut out = Vec::new();
+    for block in &info.blocks {
+        for instr in &block.instructions {
+            let (opcode, arg) = encode_instruction(instr, &block_offsets)?;
+            out.push(opcode);
+            out.push(arg);
+        }
+    }
+    Ok(out)
+}
+
+/// Map one instruction to a `(opcode, arg)` wordcode pair; see the module
+/// doc comment for which instructions this covers.
+fn encode_instruction(
+    instr: &ir::InstructionInfo,
+    block_offsets: &[u32],
+) -> Result<(u8, u8), PycError> {
+    let jump_arg = |target: ir::BlockIdx| -> Result<u8, PycError> {
+        let offset = block_offsets
+            .get(target.to_u32() as usize)
+            .copied()
+            .unwrap_or(0);
+        u8::try_from(offset).map_err(|_| {
+            PycError::UnsupportedInstruction(format!(
+                "jump target offset {offset} doesn't fit in a wordcode arg byte"
+            ))
+        })
+    };
+    let load_const_arg = || -> Result<u8, PycError> {
+        u8::try_from(instr.arg.0).map_err(|_| {
+            PycError::UnsupportedInstruction(format!(
+                "constant index {} doesn't fit in a wordcode arg byte",
+                instr.arg.0
+            ))
+        })
+    };
+    Ok(match &instr.instr {
+        Instruction::Pop => (POP_TOP, 0),
+        Instruction::Duplicate => (DUP_TOP, 0),
+        Instruction::ReturnValue => (RETURN_VALUE, 0),
+        Instruction::LoadConst { .. } => (LOAD_CONST, load_const_arg()?),
+        Instruction::Jump { .. } => (JUMP_ABSOLUTE, jump_arg(instr.target)?),
+        Instruction::JumpIfTrue { .. } => (POP_JUMP_IF_TRUE, jump_arg(instr.target)?),
+        Instruction::JumpIfFalse { .. } => (POP_JUMP_IF_FALSE, jump_arg(instr.target)?),
+        other => return Err(PycError::UnsupportedInstruction(format!("{other:?}"))),
+    })
+}
+
+/// A conservative, straight-line estimate of peak stack depth: walk every
+/// block's instructions summing each one's push/pop effect and track the
+/// running maximum. This doesn't account for blocks only reached via a
+/// jump needing their own starting depth (true stack-depth analysis walks
+/// the CFG); it's accurate for the straight-line, non-branching code this
+/// module's instruction subset mostly produces, and errs toward
+/// overestimating rather than under for anything more complex.
+fn estimate_stacksize(info: &ir::CodeInfo) -> u32 {
+    let mut max_depth = 1u32;
+    let mut depth = 0i64;
+    for block in &info.blocks {
+        for instr in &block.instructions {
+            depth += match &instr.instr {
+                Instruction::LoadConst { .. } | Instruction::Duplicate => 1,
+                Instruction::Pop
+                | Instruction::ReturnValue
+                | Instruction::JumpIfTrue { .. }
+                | Instruction::JumpIfFalse { .. } => -1,
+                _ => 0,
+            };
+            max_depth = max_depth.max(depth.max(0) as u32);
+        }
+    }
+    max_depth
+}
+
+fn marshal_i32(value: i32, out: &mut impl Write) -> Result<(), PycError> {
+    out.write_all(b"i")?;
+    out.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+fn marshal_bytes(bytes: &[u8], out: &mut impl Write) -> Result<(), PycError> {
+    out.write_all(b"s")?;
+    out.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    out.write_all(bytes)?;
+    Ok(())
+}
+
+fn marshal_str(s: &str, out: &mut impl Write) -> Result<(), PycError> {
+    out.write_all(b"u")?;
+    out.write_all(&(s.len() as u32).to_le_bytes())?;
+    out.write_all(s.as_bytes())?;
+    Ok(())
+}
+
+fn marshal_str_tuple(items: &[impl AsRef<str>], out: &mut impl Write) -> Result<(), PycError> {
+    out.write_all(b"(")?;
+    out.write_all(&(items.len() as u32).to_le_bytes())?;
+    for item in items {
+        marshal_str(item.as_ref(), out)?;
+    }
+    Ok(())
+}
+
+fn marshal_consts(info: &ir::CodeInfo, out: &mut impl Write) -> Result<(), PycError> {
+    let constants = info.constants.as_slice();
+    out.write_all(b"(")?;
+    out.write_all(&(constants.len() as u32).to_le_bytes())?;
+    for constant in constants {
+        marshal_constant(constant, out)?;
+    }
+    Ok(())
+}
+
+fn marshal_constant(constant: &ConstantData, out: &mut impl Write) -> Result<(), PycError> {
+    match constant {
+        ConstantData::None => out.write_all(b"N")?,
+        ConstantData::Boolean { value: true } => out.write_all(b"T")?,
+        ConstantData::Boolean { value: false } => out.write_all(b"F")?,
+        ConstantData::Ellipsis => out.write_all(b".")?,
+        ConstantData::Integer { value } => {
+            // Only the common small-int range round-trips through a
+            // single 32-bit `TYPE_INT`; CPython's real `TYPE_LONG` needs a
+            // base-2^15 digit array this module doesn't build yet.
+            match value.to_i32() {
+                Some(v) => marshal_i32(v, out)?,
+                None => {
+                    return Err(PycError::UnsupportedInstruction(
+                        "integer constant outside i32 range".to_owned(),
+                    ));
+                }
+            }
+        }
+        ConstantData::Float { value } => {
+            out.write_all(b"g")?;
+            out.write_all(&value.to_le_bytes())?;
+        }
+        ConstantData::Str { value } => marshal_str(value, out)?,
+        ConstantData::Bytes { value } => marshal_bytes(value, out)?,
+        ConstantData::Tuple { elements } => {
+            out.write_all(b"(")?;
+            out.write_all(&(elements.len() as u32).to_le_bytes())?;
+            for element in elements {
+                marshal_constant(element, out)?;
+            }
+        }
+        ConstantData::Complex { .. } | ConstantData::Code { .. } => {
+            return Err(PycError::UnsupportedInstruction(format!("{constant:?}")));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,247 @@
+//! A second [`EmitBackend`] implementation that lowers into a typed,
+//! register-based SSA form instead of `rustpython_vm`'s stack bytecode -
+//! the shape an AOT/JIT pipeline would want to consume rather than the
+//! `Instruction` stream `Compiler` itself emits.
+//!
+//! [`SsaBuilder`] only has to satisfy the same trait [`Compiler`](crate::compile::Compiler)
+//! does; it doesn't share any state or types with it. Each `emit_*` call
+//! appends one [`SsaInstr`] to the current [`SsaBlock`] and, where the
+//! trait method returns a value, hands back a fresh [`Reg`] naming the
+//! instruction's result - the SSA analogue of the stack backend's
+//! implicit operand-stack slot.
+
+use crate::emit_backend::{EmitBackend, SequenceKind};
+use rustpython_compiler_core::bytecode::{BinaryOperator, ConstantData};
+
+/// A single SSA register: the result of exactly one [`SsaInstr`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Reg(u32);
+
+/// An opaque handle to one [`SsaBlock`] in a [`SsaBuilder`]'s block list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockId(u32);
+
+#[derive(Debug, Clone)]
+pub enum SsaInstr {
+    LoadConst {
+        dest: Reg,
+        value: ConstantData,
+    },
+    BinaryOp {
+        dest: Reg,
+        op: BinaryOperator,
+        lhs: Reg,
+        rhs: Reg,
+        inplace: bool,
+    },
+    BuildSequence {
+        dest: Reg,
+        kind: SequenceKind,
+        elements: Vec<Reg>,
+    },
+    StoreName {
+        name: String,
+        value: Reg,
+    },
+    BranchIf {
+        cond: Reg,
+        target: BlockId,
+        when_true: bool,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SsaBlock {
+    pub instructions: Vec<SsaInstr>,
+}
+
+/// Builds up a block-structured SSA program one [`EmitBackend`] call at a
+/// time. There's no separate "finish and get the program" step yet since
+/// nothing downstream consumes it; `blocks` is public so a caller
+/// (an eventual `nac3`-style lowering pass, say) can walk the result
+/// directly.
+#[derive(Debug, Clone, Default)]
+pub struct SsaBuilder {
+    pub blocks: Vec<SsaBlock>,
+    current: usize,
+    next_reg: u32,
+}
+
+impl SsaBuilder {
+    pub fn new() -> Self {
+        let mut builder = Self::default();
+        builder.blocks.push(SsaBlock::default());
+        builder
+    }
+
+    fn fresh_reg(&mut self) -> Reg {
+        let reg = Reg(self.next_reg);
+        self.next_reg += 1;
+        reg
+    }
+
+    fn push(&mut self, instr: SsaInstr) {
+        self.blocks[self.current].instructions.push(instr);
+    }
+}
+
+impl EmitBackend for SsaBuilder {
+    type Value = Reg;
+    type Block = BlockId;
+
+    fn new_block(&mut self) -> Self::Block {
+        let id = BlockId(self.blocks.len().try_into().expect("too many blocks"));
+        self.blocks.push(SsaBlock::default());
+        id
+    }
+
+    fn switch_to_block(&mut self, block: Self::Block) {
+        self.current = block.0 as usize;
+    }
+
+    fn emit_load_const(&mut self, value: ConstantData) -> Self::Value {
+        let dest = self.fresh_reg();
+        self.push(SsaInstr::LoadConst { dest, value });
+        dest
+    }
+
+    fn emit_binary_op(
+        &mut self,
+        op: BinaryOperator,
+        lhs: Self::Value,
+        rhs: Self::Value,
+        inplace: bool,
+    ) -> Self::Value {
+        let dest = self.fresh_reg();
+        self.push(SsaInstr::BinaryOp {
+            dest,
+            op,
+            lhs,
+            rhs,
+            inplace,
+        });
+        dest
+    }
+
+    fn emit_jump_if(&mut self, condition: Self::Value, target: Self::Block, when_true: bool) {
+        self.push(SsaInstr::BranchIf {
+            cond: condition,
+            target,
+            when_true,
+        });
+    }
+
+    fn emit_store_name(&mut self, name: &str, value: Self::Value) {
+        self.push(SsaInstr::StoreName {
+            name: name.to_owned(),
+            value,
+        });
+    }
+
+    fn build_sequence(&mut self, kind: SequenceKind, elements: Vec<Self::Value>) -> Self::Value {
+        let dest = self.fresh_reg();
+        self.push(SsaInstr::BuildSequence {
+            dest,
+            kind,
+            elements,
+        });
+        dest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use malachite_bigint::BigInt;
+
+    #[test]
+    fn load_const_assigns_fresh_registers_in_order() {
+        let mut builder = SsaBuilder::new();
+        let a = builder.emit_load_const(ConstantData::Integer {
+            value: BigInt::from(1),
+        });
+        let b = builder.emit_load_const(ConstantData::Integer {
+            value: BigInt::from(2),
+        });
+        assert_ne!(a, b);
+        assert_eq!((a, b), (Reg(0), Reg(1)));
+        assert_eq!(builder.blocks.len(), 1);
+        assert_eq!(builder.blocks[0].instructions.len(), 2);
+    }
+
+    #[test]
+    fn binary_op_and_store_name_reference_their_operand_registers() {
+        let mut builder = SsaBuilder::new();
+        let lhs = builder.emit_load_const(ConstantData::Integer {
+            value: BigInt::from(1),
+        });
+        let rhs = builder.emit_load_const(ConstantData::Integer {
+            value: BigInt::from(2),
+        });
+        let sum = builder.emit_binary_op(BinaryOperator::Add, lhs, rhs, false);
+        builder.emit_store_name("x", sum);
+
+        match &builder.blocks[0].instructions[2] {
+            SsaInstr::BinaryOp {
+                dest,
+                op: BinaryOperator::Add,
+                lhs: got_lhs,
+                rhs: got_rhs,
+                inplace: false,
+            } => {
+                assert_eq!(*got_lhs, lhs);
+                assert_eq!(*got_rhs, rhs);
+                assert_eq!(*dest, sum);
+            }
+            other => panic!("expected BinaryOp, got {other:?}"),
+        }
+        match &builder.blocks[0].instructions[3] {
+            SsaInstr::StoreName { name, value } => {
+                assert_eq!(name, "x");
+                assert_eq!(*value, sum);
+            }
+            other => panic!("expected StoreName, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn build_sequence_collects_element_registers() {
+        let mut builder = SsaBuilder::new();
+        let a = builder.emit_load_const(ConstantData::Integer {
+            value: BigInt::from(1),
+        });
+        let b = builder.emit_load_const(ConstantData::Integer {
+            value: BigInt::from(2),
+        });
+        let list = builder.build_sequence(SequenceKind::List, vec![a, b]);
+
+        match &builder.blocks[0].instructions[2] {
+            SsaInstr::BuildSequence {
+                dest,
+                kind: SequenceKind::List,
+                elements,
+            } => {
+                assert_eq!(*dest, list);
+                assert_eq!(elements, &[a, b]);
+            }
+            other => panic!("expected BuildSequence, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn switch_to_block_targets_the_right_block() {
+        let mut builder = SsaBuilder::new();
+        let block = builder.new_block();
+        builder.switch_to_block(block);
+        let reg = builder.emit_load_const(ConstantData::Integer {
+            value: BigInt::from(1),
+        });
+
+        assert_eq!(builder.blocks[0].instructions.len(), 0);
+        assert_eq!(builder.blocks[1].instructions.len(), 1);
+        match &builder.blocks[1].instructions[0] {
+            SsaInstr::LoadConst { dest, .. } => assert_eq!(*dest, reg),
+            other => panic!("expected LoadConst, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,455 @@
+//! An optional, gradual type-checking pass over annotations, run after
+//! symbol-table construction and before bytecode emission when
+//! [`crate::compile::CompileOpts::type_check`] is set.
+//!
+//! This assigns every expression a [`Ty`], starting from what a
+//! `StmtAnnAssign`/parameter/return annotation or literal spells out, and
+//! only ever reports a conflict when two types for the *same* binding or
+//! operation provably disagree - anything it can't pin down (a name with
+//! no annotation, a call result, an attribute access, a bitwise/shift
+//! operator) stays [`Ty::Any`] and never produces a diagnostic, so
+//! unannotated code is completely unaffected. This is intentionally far
+//! short of the full nac3-style unification engine the request describes:
+//! it tracks simple `Name` annotations (`int`, `str`, `float`, `bool`,
+//! `bytes`, `complex`, `None`), `list[T]` and `X | Y` annotations, and
+//! literal/name/`BinOp`/`Compare`/`Subscript` expressions, within a single
+//! flat statement list (no cross-scope/closure tracking, no
+//! call-signature unification). Widening `infer_expr`/`Ty` is how this
+//! would grow toward the fuller design without changing the shape of
+//! `check_module`.
+
+use ruff_python_ast::{
+    CmpOp, Expr, ExprAttribute, ExprBinOp, ExprCompare, ExprSubscript, Number, Operator, Stmt,
+    StmtAnnAssign, StmtAssign, StmtAugAssign, StmtFunctionDef, StmtReturn,
+};
+use ruff_text_size::{Ranged, TextRange};
+use std::collections::HashMap;
+
+use crate::error::CodegenErrorType;
+
+/// A best-effort, gradually-resolved type for one expression or binding.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty {
+    Int,
+    Str,
+    Float,
+    Bool,
+    Bytes,
+    Complex,
+    None,
+    /// `list[T]` - tracked just deep enough to check element-subscript
+    /// reads and `list[T] + list[T]`; a bare `list` annotation gives `T =
+    /// Any`.
+    List(Box<Ty>),
+    /// `X | Y | ...` (PEP 604), assignable from/to anything at least one
+    /// member is assignable with.
+    Union(Vec<Ty>),
+    /// Unconstrained - could be anything; never conflicts with anything.
+    Any,
+}
+
+impl Ty {
+    fn from_annotation_name(name: &str) -> Self {
+        match name {
+            "int" => Self::Int,
+            "str" => Self::Str,
+            "float" => Self::Float,
+            "bool" => Self::Bool,
+            "bytes" => Self::Bytes,
+            "complex" => Self::Complex,
+            "None" => Self::None,
+            "list" | "List" => Self::List(Box::new(Self::Any)),
+            _ => Self::Any,
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Self::Int => "int".to_owned(),
+            Self::Str => "str".to_owned(),
+            Self::Float => "float".to_owned(),
+            Self::Bool => "bool".to_owned(),
+            Self::Bytes => "bytes".to_owned(),
+            Self::Complex => "complex".to_owned(),
+            Self::None => "None".to_owned(),
+            Self::List(elem) => format!("list[{}]", elem.name()),
+            Self::Union(members) => members.iter().map(Ty::name).collect::<Vec<_>>().join(" | "),
+            Self::Any => "Any".to_owned(),
+        }
+    }
+
+    /// Whether a value of this type can never be assigned where `other`
+    /// is declared (or vice versa). `Any` on either side, or a matching
+    /// `Union` member, always says no.
+    fn conflicts_with(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Any, _) | (_, Self::Any) => false,
+            (Self::Union(members), other) => members.iter().all(|m| m.conflicts_with(other)),
+            (slf, Self::Union(members)) => members.iter().all(|m| slf.conflicts_with(m)),
+            (Self::List(a), Self::List(b)) => a.conflicts_with(b),
+            (Self::List(_), _) | (_, Self::List(_)) => true,
+            // `bool` is a subtype of `int` in real Python (`x: int = True`
+            // is idiomatic), matching the numeric treatment `infer_binop`
+            // already gives `Ty::Bool`.
+            (Self::Int, Self::Bool) | (Self::Bool, Self::Int) => false,
+            _ => self != other,
+        }
+    }
+}
+
+fn annotation_ty(annotation: &Expr) -> Ty {
+    match annotation {
+        Expr::Name(name) => Ty::from_annotation_name(name.id.as_str()),
+        Expr::NoneLiteral(_) => Ty::None,
+        Expr::Subscript(ExprSubscript { value, slice, .. }) => match &**value {
+            Expr::Name(name) if matches!(name.id.as_str(), "list" | "List") => {
+                Ty::List(Box::new(annotation_ty(slice)))
+            }
+            _ => Ty::Any,
+        },
+        Expr::BinOp(ExprBinOp {
+            left,
+            op: Operator::BitOr,
+            right,
+            ..
+        }) => {
+            let mut members = Vec::new();
+            flatten_union(left, &mut members);
+            flatten_union(right, &mut members);
+            Ty::Union(members)
+        }
+        _ => Ty::Any,
+    }
+}
+
+/// Collect the flat member list of a `X | Y | Z` annotation chain, which
+/// parses as left-nested `BinOp`s.
+fn flatten_union(expr: &Expr, members: &mut Vec<Ty>) {
+    match expr {
+        Expr::BinOp(ExprBinOp {
+            left,
+            op: Operator::BitOr,
+            right,
+            ..
+        }) => {
+            flatten_union(left, members);
+            flatten_union(right, members);
+        }
+        _ => members.push(annotation_ty(expr)),
+    }
+}
+
+/// A resolved-conflict diagnostic: the error to report, and the source
+/// range of the expression that conflicted with an earlier annotation.
+pub type TypeError = (CodegenErrorType, TextRange);
+
+/// Infer the type of an expression, recursing into the small set of
+/// constructs this pass understands (literals, bound `Name`s, `BinOp`,
+/// `Compare`, `Subscript`) and reporting a provable operator/operand-type
+/// mismatch as a [`TypeError`]. Anything else - a call result, an
+/// unbound name, an unsupported construct - resolves to [`Ty::Any`] and
+/// never errors.
+fn infer_expr(expr: &Expr, bindings: &HashMap<String, (Ty, TextRange)>) -> Result<Ty, TypeError> {
+    Ok(match expr {
+        Expr::NumberLiteral(n) => match &n.value {
+            Number::Int(_) => Ty::Int,
+            Number::Float(_) => Ty::Float,
+            Number::Complex { .. } => Ty::Complex,
+        },
+        Expr::StringLiteral(_) => Ty::Str,
+        Expr::BytesLiteral(_) => Ty::Bytes,
+        Expr::BooleanLiteral(_) => Ty::Bool,
+        Expr::NoneLiteral(_) => Ty::None,
+        Expr::Name(name) => bindings
+            .get(name.id.as_str())
+            .map(|(ty, _)| ty.clone())
+            .unwrap_or(Ty::Any),
+        Expr::BinOp(ExprBinOp {
+            left, op, right, ..
+        }) => {
+            let l = infer_expr(left, bindings)?;
+            let r = infer_expr(right, bindings)?;
+            infer_binop(&l, *op, &r, expr.range())?
+        }
+        Expr::Compare(ExprCompare {
+            left,
+            ops,
+            comparators,
+            ..
+        }) => {
+            let mut prev = infer_expr(left, bindings)?;
+            for (op, comparator) in ops.iter().zip(comparators.iter()) {
+                let next = infer_expr(comparator, bindings)?;
+                let is_ordering = matches!(op, CmpOp::Lt | CmpOp::LtE | CmpOp::Gt | CmpOp::GtE);
+                // int/float/bool order against each other, as in `infer_binop`.
+                let numeric = |t: &Ty| matches!(t, Ty::Int | Ty::Float | Ty::Bool);
+                let both_numeric = numeric(&prev) && numeric(&next);
+                if is_ordering && !both_numeric && prev.conflicts_with(&next) {
+                    return Err((
+                        CodegenErrorType::SyntaxError(format!(
+                            "'{}' not supported between instances of '{}' and '{}'",
+                            cmp_op_symbol(*op),
+                            prev.name(),
+                            next.name()
+                        )),
+                        comparator.range(),
+                    ));
+                }
+                prev = next;
+            }
+            Ty::Bool
+        }
+        Expr::Subscript(ExprSubscript { value, .. }) => match infer_expr(value, bindings)? {
+            Ty::List(elem) => *elem,
+            _ => Ty::Any,
+        },
+        Expr::Attribute(ExprAttribute { .. }) => Ty::Any,
+        _ => Ty::Any,
+    })
+}
+
+/// The result type of a binary operator over two operand types, or a
+/// [`TypeError`] when the combination is one real Python provably
+/// rejects. Bitwise/shift/matrix-multiply operators aren't modeled
+/// closely enough to risk a false positive, so they always resolve to
+/// [`Ty::Any`].
+fn infer_binop(l: &Ty, op: Operator, r: &Ty, range: TextRange) -> Result<Ty, TypeError> {
+    use Operator::*;
+    if matches!(l, Ty::Any) || matches!(r, Ty::Any) {
+        return Ok(Ty::Any);
+    }
+    let numeric = |t: &Ty| matches!(t, Ty::Int | Ty::Float | Ty::Bool);
+    let int_like = |t: &Ty| matches!(t, Ty::Int | Ty::Bool);
+    let numeric_result = |l: &Ty, r: &Ty| {
+        if matches!(l, Ty::Float) || matches!(r, Ty::Float) {
+            Ty::Float
+        } else {
+            Ty::Int
+        }
+    };
+    let result = match op {
+        Add => match (l, r) {
+            (Ty::Str, Ty::Str) => Some(Ty::Str),
+            (Ty::Bytes, Ty::Bytes) => Some(Ty::Bytes),
+            (Ty::List(elem), Ty::List(other)) if !elem.conflicts_with(other) => {
+                Some(Ty::List(elem.clone()))
+            }
+            _ if numeric(l) && numeric(r) => Some(numeric_result(l, r)),
+            _ => None,
+        },
+        Sub => {
+            if numeric(l) && numeric(r) {
+                Some(numeric_result(l, r))
+            } else {
+                None
+            }
+        }
+        Mult => match (l, r) {
+            (Ty::Str, rhs) if int_like(rhs) => Some(Ty::Str),
+            (lhs, Ty::Str) if int_like(lhs) => Some(Ty::Str),
+            (Ty::Bytes, rhs) if int_like(rhs) => Some(Ty::Bytes),
+            (lhs, Ty::Bytes) if int_like(lhs) => Some(Ty::Bytes),
+            _ if numeric(l) && numeric(r) => Some(numeric_result(l, r)),
+            _ => None,
+        },
+        // True division always yields a float, even for two ints.
+        Div if numeric(l) && numeric(r) => Some(Ty::Float),
+        FloorDiv | Mod | Pow if numeric(l) && numeric(r) => Some(numeric_result(l, r)),
+        Div | FloorDiv | Mod | Pow => None,
+        LShift | RShift | BitOr | BitXor | BitAnd | MatMult => Some(Ty::Any),
+    };
+    result.ok_or_else(|| {
+        (
+            CodegenErrorType::SyntaxError(format!(
+                "unsupported operand type(s) for {}: '{}' and '{}'",
+                binop_symbol(op),
+                l.name(),
+                r.name()
+            )),
+            range,
+        )
+    })
+}
+
+fn binop_symbol(op: Operator) -> &'static str {
+    match op {
+        Operator::Add => "+",
+        Operator::Sub => "-",
+        Operator::Mult => "*",
+        Operator::MatMult => "@",
+        Operator::Div => "/",
+        Operator::FloorDiv => "//",
+        Operator::Mod => "%",
+        Operator::Pow => "**",
+        Operator::LShift => "<<",
+        Operator::RShift => ">>",
+        Operator::BitOr => "|",
+        Operator::BitXor => "^",
+        Operator::BitAnd => "&",
+    }
+}
+
+fn cmp_op_symbol(op: CmpOp) -> &'static str {
+    match op {
+        CmpOp::Eq => "==",
+        CmpOp::NotEq => "!=",
+        CmpOp::Lt => "<",
+        CmpOp::LtE => "<=",
+        CmpOp::Gt => ">",
+        CmpOp::GtE => ">=",
+        CmpOp::In => "in",
+        CmpOp::NotIn => "not in",
+        CmpOp::Is => "is",
+        CmpOp::IsNot => "is not",
+    }
+}
+
+/// Type-check a flat statement list (a module or function body), per the
+/// scope described in the module doc comment.
+///
+/// Returns the map of every node this pass could resolve a concrete type
+/// for (keyed by source range, so downstream tooling can look a
+/// particular expression up), or the first genuine conflict found.
+pub fn check_module(body: &[Stmt]) -> Result<HashMap<TextRange, Ty>, TypeError> {
+    let mut bindings: HashMap<String, (Ty, TextRange)> = HashMap::new();
+    let mut resolved = HashMap::new();
+    check_body(body, &mut bindings, &mut resolved)?;
+    Ok(resolved)
+}
+
+fn check_body(
+    body: &[Stmt],
+    bindings: &mut HashMap<String, (Ty, TextRange)>,
+    resolved: &mut HashMap<TextRange, Ty>,
+) -> Result<(), TypeError> {
+    for stmt in body {
+        match stmt {
+            Stmt::AnnAssign(StmtAnnAssign {
+                target,
+                annotation,
+                value,
+                ..
+            }) => {
+                let declared = annotation_ty(annotation);
+                resolved.insert(annotation.range(), declared.clone());
+                if let (Expr::Name(name), Some(value)) = (&**target, value) {
+                    let value_ty = infer_expr(value, bindings)?;
+                    resolved.insert(value.range(), value_ty.clone());
+                    if declared.conflicts_with(&value_ty) {
+                        return Err((
+                            CodegenErrorType::SyntaxError(format!(
+                                "annotated assignment of type {} conflicts with declared type {}",
+                                value_ty.name(),
+                                declared.name()
+                            )),
+                            value.range(),
+                        ));
+                    }
+                    bindings.insert(name.id.to_string(), (declared, annotation.range()));
+                } else if let Expr::Name(name) = &**target {
+                    bindings.insert(name.id.to_string(), (declared, annotation.range()));
+                }
+            }
+            Stmt::Assign(StmtAssign { targets, value, .. }) => {
+                let value_ty = infer_expr(value, bindings)?;
+                resolved.insert(value.range(), value_ty.clone());
+                for target in targets {
+                    if let Expr::Name(name) = target {
+                        if let Some((declared, _)) = bindings.get(name.id.as_str()) {
+                            if declared.conflicts_with(&value_ty) {
+                                return Err((
+                                    CodegenErrorType::SyntaxError(format!(
+                                        "assigning {} conflicts with type {} declared at an earlier annotation",
+                                        value_ty.name(),
+                                        declared.name()
+                                    )),
+                                    value.range(),
+                                ));
+                            }
+                        }
+                    }
+                }
+            }
+            Stmt::AugAssign(StmtAugAssign {
+                target, op, value, ..
+            }) => {
+                let value_ty = infer_expr(value, bindings)?;
+                resolved.insert(value.range(), value_ty.clone());
+                if let Expr::Name(name) = &**target {
+                    if let Some((declared, _)) = bindings.get(name.id.as_str()) {
+                        let result_ty = infer_binop(declared, *op, &value_ty, value.range())?;
+                        if declared.conflicts_with(&result_ty) {
+                            return Err((
+                                CodegenErrorType::SyntaxError(format!(
+                                    "augmented assignment produces {} which conflicts with the declared type {}",
+                                    result_ty.name(),
+                                    declared.name()
+                                )),
+                                value.range(),
+                            ));
+                        }
+                    }
+                }
+            }
+            Stmt::FunctionDef(StmtFunctionDef {
+                parameters,
+                returns,
+                body: fn_body,
+                ..
+            }) => {
+                let return_ty = returns.as_deref().map(annotation_ty);
+                if let Some(returns) = returns {
+                    resolved.insert(returns.range(), return_ty.clone().unwrap());
+                }
+                let mut fn_bindings = HashMap::new();
+                let params_iter = std::iter::empty()
+                    .chain(&parameters.posonlyargs)
+                    .chain(&parameters.args)
+                    .chain(&parameters.kwonlyargs)
+                    .map(|x| &x.parameter);
+                for param in params_iter {
+                    if let Some(annotation) = &param.annotation {
+                        let ty = annotation_ty(annotation);
+                        resolved.insert(annotation.range(), ty.clone());
+                        fn_bindings.insert(param.name.to_string(), (ty, annotation.range()));
+                    }
+                }
+                check_function_returns(fn_body, return_ty, &fn_bindings, resolved)?;
+                check_body(fn_body, &mut fn_bindings, resolved)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn check_function_returns(
+    body: &[Stmt],
+    declared: Option<Ty>,
+    bindings: &HashMap<String, (Ty, TextRange)>,
+    resolved: &mut HashMap<TextRange, Ty>,
+) -> Result<(), TypeError> {
+    let Some(declared) = declared else {
+        return Ok(());
+    };
+    for stmt in body {
+        if let Stmt::Return(StmtReturn {
+            value: Some(value), ..
+        }) = stmt
+        {
+            let value_ty = infer_expr(value, bindings)?;
+            resolved.insert(value.range(), value_ty.clone());
+            if declared.conflicts_with(&value_ty) {
+                return Err((
+                    CodegenErrorType::SyntaxError(format!(
+                        "returning {} conflicts with the declared return type {}",
+                        value_ty.name(),
+                        declared.name()
+                    )),
+                    value.range(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
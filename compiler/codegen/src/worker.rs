@@ -0,0 +1,102 @@
+//! A thread-pool driver for compiling independent nested code objects
+//! concurrently, following NAC3's `CodeGenerator` trait + `WorkerRegistry`
+//! design: a registry of workers pulls compilation tasks off a queue, and
+//! the driver stitches each finished [`ConstantData::Code`] back into its
+//! parent once every task completes.
+//!
+//! [`WorkerRegistry::compile_all`] is genuinely concurrent - it spreads its
+//! [`CompileTask`]s across real worker threads via [`std::thread::scope`]
+//! and joins them back. What it doesn't have is a call site: the reason is
+//! the critical invariant this module's doc comment (and the request it
+//! was written for) calls out. Nested-scope resolution has to see a
+//! consistent view of enclosing scopes before a task is dispatched, and in
+//! this tree that view is `Compiler::symbol_table_stack`, populated by
+//! `push_symbol_table` pulling the next child out of
+//! `self.symbol_table_stack.last_mut().sub_tables` *in program order* as
+//! `compile_function_def`/`compile_class_def` recurse. A worker thread
+//! compiling one function body needs its own owned [`SymbolTable`]
+//! subtree handed to it up front, not a borrow of a `Vec` another thread
+//! is simultaneously popping from - so wiring a real call site up to this
+//! registry needs `Compiler` first restructured to take an owned subtree
+//! per nested scope instead of sharing one stack across the whole compile,
+//! which is a larger change than this module covers. Until that lands,
+//! `compile_all`'s tasks have to be independent closures a caller
+//! assembles by hand (each one a fresh `Compiler` over its own function's
+//! source slice, say) rather than something `compile_function_def` hands
+//! it directly mid-recursion.
+//!
+//! Parallel dispatch on top of this registry is therefore not something
+//! this crate currently delivers for real compilation - `compile_top`
+//! compiles every nested function and class body on the single calling
+//! thread, same as before this module existed. `crate::compile`'s
+//! `open_nested_code_object`/`close_nested_code_object` do give
+//! `CodeGenerator` - the trait this registry's tasks would eventually be
+//! built from - a real generic caller, but that's a single-threaded
+//! dispatch fix, not a step toward using `WorkerRegistry` itself; treat
+//! this module as a tested building block with no live call site rather
+//! than a feature in the compiler yet.
+//!
+//! [`SymbolTable`]: crate::symboltable::SymbolTable
+use rustpython_compiler_core::bytecode::ConstantData;
+
+/// One independent unit of work: compile a nested scope down to the
+/// [`ConstantData::Code`] it should occupy in its parent's constant pool.
+pub struct CompileTask<F> {
+    /// Where the finished code object belongs in the parent's constant
+    /// pool, so results can be stitched back in regardless of the order
+    /// worker threads finish in.
+    pub slot: usize,
+    pub compile: F,
+}
+
+/// Runs a fixed-size pool of OS threads that each compile a share of the
+/// given [`CompileTask`]s. See the module doc comment for why no call site
+/// in this tree constructs its tasks from a live `Compiler` yet.
+pub struct WorkerRegistry {
+    worker_count: usize,
+}
+
+impl WorkerRegistry {
+    /// `worker_count` workers, clamped to at least 1.
+    pub fn new(worker_count: usize) -> Self {
+        Self {
+            worker_count: worker_count.max(1),
+        }
+    }
+
+    /// Compile every task in `tasks` across the pool and return their
+    /// `(slot, ConstantData)` results in arbitrary order - the caller uses
+    /// `slot` to place each one back into its parent's constant pool.
+    ///
+    /// Panics if a worker thread panics while running its share of tasks.
+    pub fn compile_all<F>(&self, tasks: Vec<CompileTask<F>>) -> Vec<(usize, ConstantData)>
+    where
+        F: FnOnce() -> ConstantData + Send,
+    {
+        let chunk_size = tasks.len().div_ceil(self.worker_count).max(1);
+        let mut chunks: Vec<Vec<CompileTask<F>>> = Vec::new();
+        for task in tasks {
+            match chunks.last_mut() {
+                Some(chunk) if chunk.len() < chunk_size => chunk.push(task),
+                _ => chunks.push(vec![task]),
+            }
+        }
+
+        std::thread::scope(|scope| {
+            chunks
+                .into_iter()
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .into_iter()
+                            .map(|task| (task.slot, (task.compile)()))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("worker thread panicked"))
+                .collect()
+        })
+    }
+}
@@ -9,15 +9,24 @@ mod _js {
         weak_vm,
     };
     use js_sys::{Array, Object, Promise, Reflect};
+    use malachite_bigint::BigInt;
     use rustpython_vm::{
         Py, PyObjectRef, PyPayload, PyRef, PyResult, TryFromObject, VirtualMachine,
         builtins::{PyBaseExceptionRef, PyFloat, PyStrRef, PyType, PyTypeRef},
         convert::{IntoObject, ToPyObject},
-        function::{ArgCallable, OptionalArg, OptionalOption, PosArgs},
+        function::{ArgBytesLike, ArgCallable, ArgIterable, OptionalArg, OptionalOption, PosArgs},
         protocol::PyIterReturn,
         types::{IterNext, Representable, SelfIter},
     };
-    use std::{cell, fmt, future};
+    use futures::{stream::FuturesUnordered, task::noop_waker, StreamExt};
+    use std::{
+        cell,
+        collections::HashMap,
+        fmt, future,
+        pin::Pin,
+        rc::Rc,
+        task::{Context, Poll},
+    };
     use wasm_bindgen::{JsCast, closure::Closure, prelude::*};
     use wasm_bindgen_futures::{JsFuture, future_to_promise};
 
@@ -34,6 +43,24 @@ mod _js {
             closure(this, args)
         }
     }
+    export function dynamic_import(specifier) { return import(specifier); }
+    export function promise_all(arr) { return Promise.all(arr); }
+    export function promise_race(arr) { return Promise.race(arr); }
+    export function promise_any(arr) { return Promise.any(arr); }
+    export function promise_all_settled(arr) { return Promise.allSettled(arr); }
+    export function js_get_iterator(obj) {
+        if (typeof obj[Symbol.iterator] !== 'function') {
+            throw new TypeError('object is not iterable');
+        }
+        return obj[Symbol.iterator]();
+    }
+    export function js_get_async_iterator(obj) {
+        if (typeof obj[Symbol.asyncIterator] !== 'function') {
+            throw new TypeError('object is not async iterable');
+        }
+        return obj[Symbol.asyncIterator]();
+    }
+    export function js_iter_next(iter) { return iter.next(); }
     ")]
     extern "C" {
         #[wasm_bindgen(catch)]
@@ -52,6 +79,22 @@ mod _js {
         fn call_method(obj: &JsValue, method: &JsValue, args: &Array) -> Result<JsValue, JsValue>;
         #[wasm_bindgen]
         fn wrap_closure(closure: &JsValue) -> JsValue;
+        #[wasm_bindgen]
+        fn dynamic_import(specifier: &str) -> Promise;
+        #[wasm_bindgen]
+        fn promise_all(arr: &Array) -> Promise;
+        #[wasm_bindgen]
+        fn promise_race(arr: &Array) -> Promise;
+        #[wasm_bindgen]
+        fn promise_any(arr: &Array) -> Promise;
+        #[wasm_bindgen]
+        fn promise_all_settled(arr: &Array) -> Promise;
+        #[wasm_bindgen(catch)]
+        fn js_get_iterator(obj: &JsValue) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        fn js_get_async_iterator(obj: &JsValue) -> Result<JsValue, JsValue>;
+        #[wasm_bindgen(catch)]
+        fn js_iter_next(iter: &JsValue) -> Result<JsValue, JsValue>;
     }
 
     #[pyattr]
@@ -246,6 +289,73 @@ mod _js {
             self.value.as_bool()
         }
 
+        /// Round-trips a JS `BigInt` to a Python `int` through its decimal
+        /// string representation, preserving arbitrary precision (unlike
+        /// `as_float`, which would lose precision past 2**53).
+        #[pymethod]
+        fn as_int(&self, vm: &VirtualMachine) -> PyResult<Option<BigInt>> {
+            if type_of(&self.value) != "bigint" {
+                return Ok(None);
+            }
+            let digits = js_sys::BigInt::from(self.value.clone())
+                .to_string(10)
+                .map_err(|err| new_js_error(vm, err))?;
+            BigInt::parse_bytes(String::from(digits).as_bytes(), 10)
+                .map(Some)
+                .ok_or_else(|| vm.new_value_error("invalid BigInt digits"))
+        }
+
+        #[pymethod]
+        fn new_from_int(&self, n: BigInt, vm: &VirtualMachine) -> PyResult<PyJsValue> {
+            let big = js_sys::BigInt::new(&JsValue::from_str(&n.to_str_radix(10)))
+                .map_err(|err| new_js_error(vm, err))?;
+            Ok(PyJsValue::new(big))
+        }
+
+        /// Converts a `Uint8Array`/`ArrayBuffer` to Python `bytes` by
+        /// copying its backing buffer; `None` if the value is neither.
+        #[pymethod]
+        fn as_bytes(&self) -> Option<Vec<u8>> {
+            if let Some(arr) = self.value.dyn_ref::<js_sys::Uint8Array>() {
+                Some(arr.to_vec())
+            } else {
+                self.value
+                    .dyn_ref::<js_sys::ArrayBuffer>()
+                    .map(|buf| js_sys::Uint8Array::new(buf).to_vec())
+            }
+        }
+
+        #[pymethod]
+        fn new_from_bytes(&self, bytes: ArgBytesLike) -> PyJsValue {
+            PyJsValue::new(js_sys::Uint8Array::from(bytes.borrow_buf().as_ref()))
+        }
+
+        /// Deeply converts this value to Python, recursing into plain JS
+        /// objects and arrays (dicts/lists) rather than leaving them as
+        /// opaque `PyJsValue`s; anything else falls back to `convert::js_to_py`.
+        #[pymethod]
+        fn to_py(&self, vm: &VirtualMachine) -> PyObjectRef {
+            deep_js_to_py(vm, self.value.clone())
+        }
+
+        /// Deeply converts a Python dict/list (and anything nested inside)
+        /// into the equivalent JS object/array; anything else falls back
+        /// to `convert::py_to_js`.
+        #[pymethod]
+        fn from_py(&self, obj: PyObjectRef, vm: &VirtualMachine) -> PyJsValue {
+            PyJsValue::new(deep_py_to_js(vm, obj))
+        }
+
+        /// Builds a JS `Array` out of a Python iterable of `JSValue`s.
+        #[pymethod]
+        fn new_array(&self, iterable: ArgIterable<PyJsValueRef>, vm: &VirtualMachine) -> PyResult<PyJsValue> {
+            let arr = Array::new();
+            for item in iterable.iter(vm)? {
+                arr.push(&item?.value);
+            }
+            Ok(PyJsValue::new(arr))
+        }
+
         #[pymethod(name = "typeof")]
         fn type_of(&self) -> String {
             type_of(&self.value)
@@ -262,6 +372,187 @@ mod _js {
         fn instanceof(&self, rhs: PyJsValueRef, vm: &VirtualMachine) -> PyResult<bool> {
             instance_of(&self.value, &rhs.value).map_err(|err| new_js_error(vm, err))
         }
+
+        /// Mirrors `Object.defineProperty`, letting Python create accessor
+        /// properties and non-enumerable/non-writable/non-configurable
+        /// fields that plain `set_prop` assignment can't express.
+        #[pymethod]
+        fn define_property(
+            &self,
+            name: JsProperty,
+            descriptor: PropertyDescriptorArgs,
+            vm: &VirtualMachine,
+        ) -> PyResult<()> {
+            if descriptor.value.is_some() && (descriptor.get.is_some() || descriptor.set.is_some())
+            {
+                return Err(vm.new_value_error(
+                    "Invalid property descriptor. Cannot both specify accessors and a value",
+                ));
+            }
+            let desc = Object::new();
+            let set_field = |key: &str, value: JsValue| -> PyResult<()> {
+                Reflect::set(&desc, &key.into(), &value).map_err(|err| new_js_error(vm, err))?;
+                Ok(())
+            };
+            if let Some(value) = descriptor.value {
+                set_field("value", value.value.clone())?;
+            }
+            if let Some(get) = descriptor.get {
+                set_field("get", get.value.clone())?;
+            }
+            if let Some(set) = descriptor.set {
+                set_field("set", set.value.clone())?;
+            }
+            if let Some(writable) = descriptor.writable {
+                set_field("writable", writable.into())?;
+            }
+            if let Some(enumerable) = descriptor.enumerable {
+                set_field("enumerable", enumerable.into())?;
+            }
+            if let Some(configurable) = descriptor.configurable {
+                set_field("configurable", configurable.into())?;
+            }
+            Reflect::define_property(self.value.unchecked_ref(), &name.into_js_value(), &desc)
+                .map_err(|err| new_js_error(vm, err))?;
+            Ok(())
+        }
+
+        /// Mirrors `Object.getOwnPropertyDescriptor`; returns `None` if the
+        /// object has no own property with that name.
+        #[pymethod]
+        fn get_own_property_descriptor(
+            &self,
+            name: JsProperty,
+            vm: &VirtualMachine,
+        ) -> PyResult<Option<PyJsValue>> {
+            let desc = Reflect::get_own_property_descriptor(
+                self.value.unchecked_ref(),
+                &name.into_js_value(),
+            )
+            .map_err(|err| new_js_error(vm, err))?;
+            Ok((!desc.is_undefined()).then(|| PyJsValue::new(desc)))
+        }
+
+        /// Mirrors `Object.keys`, returning the object's own enumerable
+        /// property names so Python code can enumerate a JS object.
+        #[pymethod]
+        fn own_keys(&self, vm: &VirtualMachine) -> Vec<PyObjectRef> {
+            Object::keys(self.value.unchecked_ref())
+                .iter()
+                .map(|key| PyJsValue::new(key).to_pyobject(vm))
+                .collect()
+        }
+
+        /// Resolve and evaluate a JS ES module by specifier, returning a
+        /// `Promise` that resolves to its exports namespace. Diamond/circular
+        /// imports of the same resolved specifier share one cached module.
+        #[pymethod]
+        fn import_module(&self, specifier: PyStrRef, vm: &VirtualMachine) -> PyPromise {
+            import_module(specifier.as_str(), None, vm)
+        }
+
+        /// Supports `for x in js_value`, driven by the object's
+        /// `Symbol.iterator` (arrays, Maps, Sets, generators, ...).
+        #[pymethod(name = "__iter__")]
+        fn iter(&self, vm: &VirtualMachine) -> PyResult<JsIterator> {
+            let iter = js_get_iterator(&self.value).map_err(|err| new_js_error(vm, err))?;
+            Ok(JsIterator { iter })
+        }
+
+        /// Supports `async for x in js_value`, driven by the object's
+        /// `Symbol.asyncIterator` (web streams, async generators, ...).
+        #[pymethod]
+        fn js_async_iter(&self, vm: &VirtualMachine) -> PyResult<JsAsyncIterator> {
+            let iter = js_get_async_iterator(&self.value).map_err(|err| new_js_error(vm, err))?;
+            Ok(JsAsyncIterator { iter })
+        }
+
+        /// Register a `JsModuleLoader` (any object with `resolve(specifier,
+        /// referrer)` and `load(specifier)` methods) to use for subsequent
+        /// `import_module` calls, e.g. to serve virtual or bundled modules.
+        #[pymethod]
+        fn set_module_loader(&self, loader: PyObjectRef, vm: &VirtualMachine) -> PyResult<()> {
+            let wasm_vm = WASMVirtualMachine {
+                id: vm.wasm_id.clone().unwrap(),
+            };
+            MODULE_LOADER.with(|cell| {
+                *cell.borrow_mut() = Rc::new(PyModuleLoader { wasm_vm, loader });
+            });
+            Ok(())
+        }
+    }
+
+    /// Resolves specifiers to loaded JS modules, evaluated via the current
+    /// [`JsModuleLoader`]. Modeled on Deno's `ModuleMap`: keyed by resolved
+    /// specifier so diamond/circular imports only load and evaluate once.
+    trait JsModuleLoader {
+        fn resolve(&self, specifier: &str, referrer: Option<&str>) -> String;
+        fn load(&self, specifier: &str, vm: &VirtualMachine) -> PyPromise;
+    }
+
+    /// Default loader for browser hosts: resolves specifiers as-is and loads
+    /// modules through the host's dynamic `import()`, which already handles
+    /// fetching, compiling, and recursively loading static dependencies.
+    struct BrowserModuleLoader;
+
+    impl JsModuleLoader for BrowserModuleLoader {
+        fn resolve(&self, specifier: &str, _referrer: Option<&str>) -> String {
+            specifier.to_owned()
+        }
+        fn load(&self, specifier: &str, _vm: &VirtualMachine) -> PyPromise {
+            PyPromise::new(dynamic_import(specifier))
+        }
+    }
+
+    /// Adapts a Python object exposing `resolve`/`load` methods to the
+    /// `JsModuleLoader` trait, for user-registered virtual/bundled loaders.
+    struct PyModuleLoader {
+        wasm_vm: WASMVirtualMachine,
+        loader: PyObjectRef,
+    }
+
+    impl JsModuleLoader for PyModuleLoader {
+        fn resolve(&self, specifier: &str, referrer: Option<&str>) -> String {
+            stored_vm_from_wasm(&self.wasm_vm).interp.enter(|vm| {
+                vm.call_method(&self.loader, "resolve", (specifier, referrer))
+                    .and_then(|res| res.str(vm))
+                    .map(|s| s.as_str().to_owned())
+                    .unwrap_or_else(|_| specifier.to_owned())
+            })
+        }
+        fn load(&self, specifier: &str, _vm: &VirtualMachine) -> PyPromise {
+            stored_vm_from_wasm(&self.wasm_vm).interp.enter(|vm| {
+                match vm
+                    .call_method(&self.loader, "load", (specifier.to_owned(),))
+                    .and_then(|res| PyPromise::cast(res, vm))
+                {
+                    Ok(prom) => prom,
+                    Err(err) => PyPromise {
+                        value: PromiseKind::PyRejected(err),
+                    },
+                }
+            })
+        }
+    }
+
+    thread_local! {
+        static MODULE_LOADER: cell::RefCell<Rc<dyn JsModuleLoader>> =
+            cell::RefCell::new(Rc::new(BrowserModuleLoader));
+        static MODULE_MAP: cell::RefCell<HashMap<String, JsValue>> =
+            cell::RefCell::new(HashMap::new());
+    }
+
+    fn import_module(specifier: &str, referrer: Option<&str>, vm: &VirtualMachine) -> PyPromise {
+        let loader = MODULE_LOADER.with(|cell| cell.borrow().clone());
+        let resolved = loader.resolve(specifier, referrer);
+        if let Some(ns) = MODULE_MAP.with(|map| map.borrow().get(&resolved).cloned()) {
+            return PyPromise::new(Promise::resolve(&ns));
+        }
+        // Cache the pending load itself, so an import of the same specifier
+        // issued before the first one settles reuses it instead of loading again.
+        let load = loader.load(&resolved, vm).as_js(vm);
+        MODULE_MAP.with(|map| map.borrow_mut().insert(resolved, load.clone().into()));
+        PyPromise::new(load)
     }
 
     impl Representable for PyJsValue {
@@ -271,6 +562,165 @@ mod _js {
         }
     }
 
+    fn deep_js_to_py(vm: &VirtualMachine, value: JsValue) -> PyObjectRef {
+        if let Some(arr) = value.dyn_ref::<Array>() {
+            let list = arr.iter().map(|item| deep_js_to_py(vm, item)).collect();
+            vm.ctx.new_list(list).into()
+        } else if value.is_object() && {
+            // Plain objects only: prototype is `Object.prototype` or null.
+            let proto = JsValue::from(Object::get_prototype_of(&value));
+            proto.is_null() || proto == JsValue::from(Object::get_prototype_of(&Object::new()))
+        } {
+            let dict = vm.ctx.new_dict();
+            for key in Object::keys(value.unchecked_ref()).iter() {
+                let Ok(item) = get_prop(&value, &key) else {
+                    continue;
+                };
+                let _ = dict.set_item(
+                    &key.as_string().unwrap_or_default(),
+                    deep_js_to_py(vm, item),
+                    vm,
+                );
+            }
+            dict.into()
+        } else {
+            convert::js_to_py(vm, value)
+        }
+    }
+
+    fn deep_py_to_js(vm: &VirtualMachine, obj: PyObjectRef) -> JsValue {
+        if let Some(list) = obj.downcast_ref::<rustpython_vm::builtins::PyList>() {
+            let arr = Array::new();
+            for item in list.borrow_vec().iter() {
+                arr.push(&deep_py_to_js(vm, item.clone()));
+            }
+            arr.into()
+        } else if let Some(dict) = obj.downcast_ref::<rustpython_vm::builtins::PyDict>() {
+            let object = Object::new();
+            for (key, value) in dict {
+                if let Ok(key) = key.str(vm) {
+                    let _ = Reflect::set(&object, &key.as_str().into(), &deep_py_to_js(vm, value));
+                }
+            }
+            object.into()
+        } else {
+            convert::py_to_js(vm, obj)
+        }
+    }
+
+    fn js_iter_done(record: &JsValue) -> Result<bool, JsValue> {
+        Ok(get_prop(record, &"done".into())?.as_bool().unwrap_or(false))
+    }
+
+    /// Returned by `PyJsValue.__iter__`; steps the wrapped JS iterator's
+    /// `next()` and translates `{value, done}` into Python's iterator
+    /// protocol, reusing the `IterNext`/`SelfIter` machinery `AwaitPromise`
+    /// already relies on.
+    #[pyclass(no_attr, module = "_js", name = "JSIterator")]
+    #[derive(Debug, PyPayload)]
+    struct JsIterator {
+        iter: JsValue,
+    }
+
+    #[pyclass(with(IterNext, SelfIter))]
+    impl JsIterator {}
+
+    impl SelfIter for JsIterator {}
+
+    impl IterNext for JsIterator {
+        fn next(zelf: &Py<Self>, vm: &VirtualMachine) -> PyResult<PyIterReturn> {
+            let record = js_iter_next(&zelf.iter).map_err(|err| new_js_error(vm, err))?;
+            if js_iter_done(&record).map_err(|err| new_js_error(vm, err))? {
+                Ok(PyIterReturn::StopIteration(None))
+            } else {
+                let value = get_prop(&record, &"value".into()).map_err(|err| new_js_error(vm, err))?;
+                Ok(PyIterReturn::Return(PyJsValue::new(value).to_pyobject(vm)))
+            }
+        }
+    }
+
+    /// Returned by `PyJsValue.js_async_iter`; drives the wrapped JS
+    /// `Symbol.asyncIterator`'s `next()` promise through the `PyProm`
+    /// `PyPromise` variant, so `await`ing `__anext__`'s result raises a
+    /// genuine `StopAsyncIteration` (rather than a `JSError`) once the
+    /// JS side reports `done`.
+    #[pyclass(no_attr, module = "_js", name = "JSAsyncIterator")]
+    #[derive(Debug, PyPayload)]
+    struct JsAsyncIterator {
+        iter: JsValue,
+    }
+
+    #[pyclass]
+    impl JsAsyncIterator {
+        #[pymethod(name = "__aiter__")]
+        fn aiter(zelf: PyRef<Self>) -> PyRef<Self> {
+            zelf
+        }
+
+        #[pymethod(name = "__anext__")]
+        fn anext(&self, vm: &VirtualMachine) -> PyPromise {
+            let iter = self.iter.clone();
+            let id = vm.wasm_id.clone().unwrap();
+            let then = vm.new_function(
+                "then",
+                move |on_fulfill: OptionalArg<ArgCallable>,
+                      on_reject: OptionalArg<ArgCallable>,
+                      _vm: &VirtualMachine| {
+                    let next = js_iter_next(&iter);
+                    let wasm_vm = WASMVirtualMachine { id: id.clone() };
+                    wasm_bindgen_futures::spawn_local(async move {
+                        let settled = match next {
+                            Ok(next) => JsFuture::from(Promise::from(next)).await,
+                            Err(err) => Err(err),
+                        };
+                        stored_vm_from_wasm(&wasm_vm).interp.enter(move |vm| match settled {
+                            Ok(record) => match js_iter_done(&record) {
+                                Ok(true) => {
+                                    if let OptionalArg::Present(reject) = on_reject {
+                                        let exc = vm.new_exception_empty(
+                                            vm.ctx.exceptions.stop_async_iteration.to_owned(),
+                                        );
+                                        let _ = reject.invoke((exc,), vm);
+                                    }
+                                }
+                                Ok(false) => match get_prop(&record, &"value".into()) {
+                                    Ok(value) => {
+                                        if let OptionalArg::Present(resolve) = on_fulfill {
+                                            let _ = resolve
+                                                .invoke((PyJsValue::new(value).to_pyobject(vm),), vm);
+                                        }
+                                    }
+                                    Err(err) => {
+                                        if let OptionalArg::Present(reject) = on_reject {
+                                            let _ = reject
+                                                .invoke((new_js_error(vm, err).to_pyobject(vm),), vm);
+                                        }
+                                    }
+                                },
+                                Err(err) => {
+                                    if let OptionalArg::Present(reject) = on_reject {
+                                        let _ =
+                                            reject.invoke((new_js_error(vm, err).to_pyobject(vm),), vm);
+                                    }
+                                }
+                            },
+                            Err(err) => {
+                                if let OptionalArg::Present(reject) = on_reject {
+                                    let _ = reject.invoke((new_js_error(vm, err).to_pyobject(vm),), vm);
+                                }
+                            }
+                        });
+                    });
+                },
+            );
+            PyPromise {
+                value: PromiseKind::PyProm {
+                    then: then.to_pyobject(vm),
+                },
+            }
+        }
+    }
+
     #[derive(FromArgs)]
     struct CallOptions {
         #[pyarg(named, default)]
@@ -283,6 +733,22 @@ mod _js {
         prototype: Option<PyJsValueRef>,
     }
 
+    #[derive(FromArgs)]
+    struct PropertyDescriptorArgs {
+        #[pyarg(named, default)]
+        value: Option<PyJsValueRef>,
+        #[pyarg(named, default)]
+        get: Option<PyJsValueRef>,
+        #[pyarg(named, default)]
+        set: Option<PyJsValueRef>,
+        #[pyarg(named, default)]
+        writable: Option<bool>,
+        #[pyarg(named, default)]
+        enumerable: Option<bool>,
+        #[pyarg(named, default)]
+        configurable: Option<bool>,
+    }
+
     type ClosureType = Closure<dyn FnMut(JsValue, Box<[JsValue]>) -> Result<JsValue, JsValue>>;
 
     #[pyattr]
@@ -398,6 +864,14 @@ mod _js {
                 value: PromiseKind::Js(value),
             }
         }
+        // Driven by `wasm_bindgen_futures`'s own executor, which pumps
+        // itself off the browser microtask queue - this is what makes
+        // `.then()`/`.catch()` chaining, chunk1-1's `import_module`, and
+        // chunk1-5's `JsAsyncIterator::anext` resolve on their own instead
+        // of hanging until something calls `run_until_stalled`. The
+        // `TASK_QUEUE`/`spawn_local` machinery below is for
+        // `run_until_complete`'s own explicit pump, not for driving
+        // ordinary promises.
         pub fn from_future<F>(future: F) -> PyPromise
         where
             F: future::Future<Output = Result<JsValue, JsValue>> + 'static,
@@ -468,6 +942,67 @@ mod _js {
             .into_ref_with_type(vm, cls)
         }
 
+        fn cast_iterable(iterable: ArgIterable, vm: &VirtualMachine) -> PyResult<Array> {
+            let arr = Array::new();
+            for obj in iterable.iter(vm)? {
+                arr.push(&Self::cast(obj?, vm)?.as_js(vm));
+            }
+            Ok(arr)
+        }
+
+        #[pyclassmethod]
+        fn all(cls: PyTypeRef, iterable: ArgIterable, vm: &VirtualMachine) -> PyResult<PyRef<Self>> {
+            let arr = Self::cast_iterable(iterable, vm)?;
+            Self::new(promise_all(&arr)).into_ref_with_type(vm, cls)
+        }
+
+        #[pyclassmethod]
+        fn race(cls: PyTypeRef, iterable: ArgIterable, vm: &VirtualMachine) -> PyResult<PyRef<Self>> {
+            let arr = Self::cast_iterable(iterable, vm)?;
+            Self::new(promise_race(&arr)).into_ref_with_type(vm, cls)
+        }
+
+        #[pyclassmethod]
+        fn any(cls: PyTypeRef, iterable: ArgIterable, vm: &VirtualMachine) -> PyResult<PyRef<Self>> {
+            let arr = Self::cast_iterable(iterable, vm)?;
+            Self::new(promise_any(&arr)).into_ref_with_type(vm, cls)
+        }
+
+        #[pyclassmethod(name = "allSettled")]
+        fn all_settled(
+            cls: PyTypeRef,
+            iterable: ArgIterable,
+            vm: &VirtualMachine,
+        ) -> PyResult<PyRef<Self>> {
+            let arr = Self::cast_iterable(iterable, vm)?;
+            Self::new(promise_all_settled(&arr)).into_ref_with_type(vm, cls)
+        }
+
+        #[pymethod(name = "finally")]
+        fn finally(&self, handler: ArgCallable, vm: &VirtualMachine) -> PyResult<PyPromise> {
+            let fulfill_handler = handler.clone();
+            let on_fulfill = vm
+                .new_function("", move |val: PyObjectRef, vm: &VirtualMachine| -> PyResult {
+                    fulfill_handler.invoke((), vm)?;
+                    Ok(val)
+                })
+                .to_pyobject(vm);
+            let on_reject = vm
+                .new_function(
+                    "",
+                    move |err: PyBaseExceptionRef, vm: &VirtualMachine| -> PyResult {
+                        handler.invoke((), vm)?;
+                        Err(err)
+                    },
+                )
+                .to_pyobject(vm);
+            self.then(
+                OptionalArg::Present(Some(ArgCallable::try_from_object(vm, on_fulfill)?)),
+                OptionalArg::Present(Some(ArgCallable::try_from_object(vm, on_reject)?)),
+                vm,
+            )
+        }
+
         #[pymethod]
         fn then(
             &self,
@@ -601,6 +1136,73 @@ mod _js {
         }
     }
 
+    type LocalTask = Pin<Box<dyn future::Future<Output = ()>>>;
+
+    thread_local! {
+        // Per-VM queue of pending Rust futures, driven explicitly by
+        // `run_until_stalled`/`run_until_complete` rather than relying on a
+        // host JS microtask queue, so `_js` promises make progress in
+        // embeddings (e.g. plain WASI) that don't have one.
+        static TASK_QUEUE: cell::RefCell<FuturesUnordered<LocalTask>> =
+            cell::RefCell::new(FuturesUnordered::new());
+    }
+
+    fn spawn_local(future: impl future::Future<Output = ()> + 'static) {
+        TASK_QUEUE.with(|queue| queue.borrow_mut().push(Box::pin(future)));
+    }
+
+    /// Polls the task queue to quiescence with a no-op waker, returning
+    /// whether any task made progress.
+    fn drain_queue() -> bool {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut progressed = false;
+        TASK_QUEUE.with(|queue| {
+            let mut queue = queue.borrow_mut();
+            while let Poll::Ready(Some(())) = Pin::new(&mut *queue).poll_next(&mut cx) {
+                progressed = true;
+            }
+        });
+        progressed
+    }
+
+    /// Polls the event loop until no queued task can make further progress
+    /// without blocking. Exposed so non-browser hosts can pump `_js`
+    /// promises and `__await__`ed coroutines deterministically.
+    #[pyfunction]
+    fn run_until_stalled() {
+        while drain_queue() {}
+    }
+
+    /// Drives the event loop until `promise` settles, returning its
+    /// resolved value or raising its rejection as a `JSError`. Errors if
+    /// the queue stalls before the promise settles (e.g. it depends on a
+    /// host callback, like `setTimeout`, that nothing here can drive).
+    #[pyfunction]
+    fn run_until_complete(promise: PyRef<PyPromise>, vm: &VirtualMachine) -> PyResult {
+        let settled: Rc<cell::RefCell<Option<Result<JsValue, JsValue>>>> =
+            Rc::new(cell::RefCell::new(None));
+        let slot = settled.clone();
+        let js_promise = JsFuture::from(promise.as_js(vm));
+        spawn_local(async move {
+            *slot.borrow_mut() = Some(js_promise.await);
+        });
+        loop {
+            if settled.borrow().is_some() {
+                break;
+            }
+            if !drain_queue() {
+                return Err(vm.new_value_error(
+                    "event loop stalled before the promise settled",
+                ));
+            }
+        }
+        match settled.borrow_mut().take().unwrap() {
+            Ok(val) => Ok(convert::js_to_py(vm, val)),
+            Err(err) => Err(new_js_error(vm, err)),
+        }
+    }
+
     fn new_js_error(vm: &VirtualMachine, err: JsValue) -> PyBaseExceptionRef {
         vm.new_exception(
             vm.class("_js", "JSError"),